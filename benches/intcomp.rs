@@ -0,0 +1,86 @@
+use aoc2019::intcomp::{bench, read_program, Intcomp};
+use criterion::{criterion_group, criterion_main, Criterion};
+use permutohedron::Heap;
+use std::hint::black_box;
+
+/// The day 2 search: a fresh machine for each of 10,000 noun/verb pairs.
+fn day02_brute_force(c: &mut Criterion) {
+    let program = read_program("day02.input.txt");
+
+    c.bench_function("day02 brute force", |b| {
+        b.iter(|| {
+            for noun in 0..100 {
+                for verb in 0..100 {
+                    let mut intcomp = Intcomp::new(&program);
+
+                    intcomp.write_memory(1, noun);
+                    intcomp.write_memory(2, verb);
+                    intcomp.execute().expect("execution failed");
+
+                    black_box(intcomp.read_memory(0));
+                }
+            }
+        })
+    });
+}
+
+/// The day 7 search: five chained amplifiers for each of 120 phase settings.
+fn day07_amplifiers(c: &mut Criterion) {
+    let program = read_program("day07.input.txt");
+
+    c.bench_function("day07 amplifiers", |b| {
+        b.iter(|| {
+            for phase_settings in Heap::new(&mut vec![0, 1, 2, 3, 4]) {
+                let mut signal = 0;
+
+                for phase_setting in phase_settings {
+                    let mut intcomp = Intcomp::new(&program);
+
+                    intcomp.send_input(phase_setting);
+                    intcomp.send_input(signal);
+                    intcomp.execute().expect("execution failed");
+
+                    signal = intcomp.receive_output().expect("no output available");
+                }
+
+                black_box(signal);
+            }
+        })
+    });
+}
+
+/// A single long-running program: the day 9 boost program's sensor mode.
+fn day09_sensor_boost(c: &mut Criterion) {
+    let program = read_program("day09.input.txt");
+
+    c.bench_function("day09 sensor boost", |b| {
+        b.iter(|| {
+            let mut intcomp = Intcomp::new(&program);
+
+            intcomp.send_input(2);
+            intcomp.execute().expect("execution failed");
+
+            black_box(intcomp.receive_output());
+        })
+    });
+}
+
+/// Parsing each instruction digit by digit against looking it up in the
+/// table of decoded instructions, over every word of the day 9 program.
+fn instruction_decoding(c: &mut Criterion) {
+    let program = read_program("day09.input.txt");
+    let mut group = c.benchmark_group("instruction decoding");
+
+    group.bench_function("parse", |b| b.iter(|| bench::parse(black_box(&program))));
+    group.bench_function("decode", |b| b.iter(|| bench::decode(black_box(&program))));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    day02_brute_force,
+    day07_amplifiers,
+    day09_sensor_boost,
+    instruction_decoding
+);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+//! Run arbitrary words as a program.  Every way a program can go wrong should
+//! come back as an `IntcompError`, so any panic here is a bug.  The limits
+//! keep runaway programs from looping, allocating or printing forever.
+
+#![no_main]
+
+use aoc2019::intcomp::Intcomp;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|program: Vec<i64>| {
+    let mut intcomp = Intcomp::new(&program);
+
+    intcomp.set_memory_limit(Some(1 << 16));
+    intcomp.set_instruction_limit(Some(100_000));
+    intcomp.set_output_limit(Some(1000));
+
+    let _ = intcomp.execute();
+});
@@ -1,35 +1,31 @@
-use crate::intcomp::{read_program, Intcomp};
+use crate::intcomp::solver::Solver;
+use crate::intcomp::{read_program, Intcomp, IntcompError};
 
 const INPUT_PATH: &str = "day02.input.txt";
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
 
     intcomp.write_memory(1, 12);
     intcomp.write_memory(2, 2);
-    intcomp.execute();
+    intcomp.execute()?;
 
     println!("Value at position 0: {}", intcomp.read_memory(0));
-}
-
-pub fn part2() {
-    let initializer = read_program(INPUT_PATH);
 
-    for noun in 0..100 {
-        for verb in 0..100 {
-            let mut intcomp = Intcomp::new(&initializer);
+    Ok(())
+}
 
-            intcomp.write_memory(1, noun);
-            intcomp.write_memory(2, verb);
-            intcomp.execute();
+pub fn part2() -> Result<(), IntcompError> {
+    let mut solver = Solver::new(&read_program(INPUT_PATH));
 
-            if intcomp.read_memory(0) == 1969_0720 {
-                println!("Target noun: {}", noun);
-                println!("Target verb: {}", verb);
+    solver.patch(1, 0..=99);
+    solver.patch(2, 0..=99);
 
-                return;
-            }
-        }
+    if let Some(solution) = solver.solve(0, 1969_0720) {
+        println!("Target noun: {}", solution.values[0]);
+        println!("Target verb: {}", solution.values[1]);
     }
+
+    Ok(())
 }
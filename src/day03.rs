@@ -67,7 +67,7 @@ fn parse_segments(source: &str) -> Vec<Segment> {
     source
         .split(',')
         .map(|segment| {
-            let direction = segment.chars().nth(0).unwrap();
+            let direction = segment.chars().next().unwrap();
             let distance = segment.chars().skip(1).collect::<String>();
 
             Segment {
@@ -201,7 +201,7 @@ mod tests {
                     distance: 3,
                 },
             ],
-            parse_segments(&"R8,U5,L5,D3".to_string())
+            parse_segments("R8,U5,L5,D3")
         );
     }
 }
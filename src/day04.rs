@@ -120,24 +120,24 @@ mod tests {
 
     #[test]
     fn has_pair_works() {
-        assert_eq!(false, has_pair(1234));
-        assert_eq!(true, has_pair(1233));
-        assert_eq!(true, has_pair(1223));
-        assert_eq!(true, has_pair(1123));
-        assert_eq!(true, has_pair(1133));
+        assert!(!has_pair(1234));
+        assert!(has_pair(1233));
+        assert!(has_pair(1223));
+        assert!(has_pair(1123));
+        assert!(has_pair(1133));
     }
 
     #[test]
     fn has_strict_pair_works() {
-        assert_eq!(false, has_strict_pair(1234));
-        assert_eq!(true, has_strict_pair(1233));
-        assert_eq!(true, has_strict_pair(1223));
-        assert_eq!(true, has_strict_pair(1123));
-        assert_eq!(true, has_strict_pair(1133));
-        assert_eq!(false, has_strict_pair(12333));
-        assert_eq!(false, has_strict_pair(12223));
-        assert_eq!(false, has_strict_pair(11123));
-        assert_eq!(true, has_strict_pair(11333));
+        assert!(!has_strict_pair(1234));
+        assert!(has_strict_pair(1233));
+        assert!(has_strict_pair(1223));
+        assert!(has_strict_pair(1123));
+        assert!(has_strict_pair(1133));
+        assert!(!has_strict_pair(12333));
+        assert!(!has_strict_pair(12223));
+        assert!(!has_strict_pair(11123));
+        assert!(has_strict_pair(11333));
     }
 
     #[test]
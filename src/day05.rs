@@ -1,13 +1,13 @@
-use crate::intcomp::{read_program, Intcomp};
+use crate::intcomp::{read_program, Intcomp, IntcompError};
 
 const INPUT_PATH: &str = "day05.input.txt";
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
 
     intcomp.send_input(1);
-    intcomp.execute();
+    intcomp.execute()?;
 
     let mut diagnostics = Vec::new();
 
@@ -23,16 +23,20 @@ pub fn part1() {
             .collect::<Vec<_>>()
             .join(",")
     );
+
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
 
     intcomp.send_input(5);
-    intcomp.execute();
+    intcomp.execute()?;
 
     while let Some(output) = intcomp.receive_output() {
         println!("Diagnostic code: {}", output);
     }
+
+    Ok(())
 }
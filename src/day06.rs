@@ -10,7 +10,7 @@ fn count_orbits(nodes: &HashMap<String, Vec<String>>, name: &str, depth: u32) ->
             depth
                 + children
                     .iter()
-                    .map(|child| count_orbits(&nodes, child, depth + 1))
+                    .map(|child| count_orbits(nodes, child, depth + 1))
                     .sum::<u32>()
         }
         None => depth,
@@ -35,7 +35,7 @@ pub fn part1() {
 
     println!(
         "Total direct and indirect orbits: {}",
-        count_orbits(&children, &"COM".to_string(), 0)
+        count_orbits(&children, "COM", 0)
     );
 }
 
@@ -1,9 +1,10 @@
-use crate::intcomp::{read_program, Intcomp};
+use crate::intcomp::pipeline::Pipeline;
+use crate::intcomp::{read_program, Intcomp, IntcompError, RunState};
 use permutohedron::Heap;
 
 const INPUT_PATH: &str = "day07.input.txt";
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut max_signal = -1;
 
@@ -15,7 +16,7 @@ pub fn part1() {
 
             intcomp.send_input(phase_setting);
             intcomp.send_input(signal);
-            intcomp.execute();
+            intcomp.execute()?;
 
             signal = intcomp.receive_output().expect("no output available");
         }
@@ -26,38 +27,54 @@ pub fn part1() {
     }
 
     println!("Max thruster signal: {}", max_signal);
+
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut max_signal = -1;
 
     for phase_settings in Heap::new(&mut vec![5, 6, 7, 8, 9]) {
-        let mut intcomps = (0..5)
-            .map(|i| {
-                let mut intcomp = Intcomp::new(&initializer);
+        let mut pipeline = Pipeline::new();
+
+        let amplifiers = phase_settings
+            .iter()
+            .map(|phase_setting| {
+                let amplifier = pipeline.add(Intcomp::new(&initializer));
 
-                intcomp.send_input(phase_settings[i]);
+                pipeline.send_input(amplifier, *phase_setting);
 
-                intcomp
+                amplifier
             })
             .collect::<Vec<_>>();
 
-        let mut signal = 0;
+        for (i, amplifier) in amplifiers.iter().enumerate() {
+            pipeline.connect(*amplifier, amplifiers[(i + 1) % amplifiers.len()]);
+        }
 
-        while !intcomps[0].is_halted() {
-            for intcomp in &mut intcomps {
-                intcomp.send_input(signal);
-                intcomp.execute();
+        pipeline.send_input(amplifiers[0], 0);
 
-                signal = intcomp.receive_output().expect("no output available");
+        let mut signal = None;
+
+        // Any amplifier failing spoils the signal, and one left waiting means
+        // the loop deadlocked before producing it.  The last amplifier's final
+        // output is the thruster signal.
+        for (index, amplifier) in pipeline.run().into_iter().enumerate() {
+            match amplifier.state? {
+                RunState::Halted => signal = amplifier.outputs.last().cloned(),
+                state => panic!("amplifier {} stopped without halting ({:?})", index, state),
             }
         }
 
+        let signal = signal.expect("no output available");
+
         if signal > max_signal {
             max_signal = signal;
         }
     }
 
     println!("Max thruster signal: {}", max_signal);
+
+    Ok(())
 }
@@ -1,5 +1,5 @@
 use std::fs;
-use std::{str, usize};
+use std::str;
 
 const HEIGHT: usize = 6;
 const INPUT_PATH: &str = "day08.input.txt";
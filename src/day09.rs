@@ -1,29 +1,33 @@
-use crate::intcomp::{read_program, Intcomp};
+use crate::intcomp::{read_program, Intcomp, IntcompError};
 
 const INPUT_PATH: &str = "day09.input.txt";
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
 
     intcomp.send_input(1);
-    intcomp.execute();
+    intcomp.execute()?;
 
     println!(
         "BOOST keycode: {}",
         intcomp.receive_output().expect("no output available")
     );
+
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
 
     intcomp.send_input(2);
-    intcomp.execute();
+    intcomp.execute()?;
 
     println!(
         "BOOST keycode: {}",
         intcomp.receive_output().expect("no output available")
     );
+
+    Ok(())
 }
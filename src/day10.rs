@@ -1,7 +1,6 @@
 use std::collections::HashSet;
 use std::f64;
 use std::fs;
-use std::i32;
 
 const HEIGHT: i32 = 33;
 const INPUT_PATH: &str = "day10.input.txt";
@@ -23,7 +22,7 @@ fn get_tracking_station(asteroids: &HashSet<(i32, i32)>) -> ((i32, i32), usize)
     let mut tracking_station = (-1, -1);
 
     for candidate in asteroids {
-        let visible_asteroids = get_visible_asteroid_vectors(*candidate, &asteroids).len();
+        let visible_asteroids = get_visible_asteroid_vectors(*candidate, asteroids).len();
 
         if visible_asteroids > max_visible_asteroids {
             max_visible_asteroids = visible_asteroids;
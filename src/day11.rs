@@ -1,45 +1,63 @@
-use crate::intcomp::{read_program, Intcomp};
+use crate::intcomp::io::IntcodeIo;
+use crate::intcomp::{read_program, Intcomp, IntcompError};
 use std::collections::HashMap;
-use std::i32;
 
 const INPUT_PATH: &str = "day11.input.txt";
 
-fn paint_hull(hull: &mut HashMap<(i32, i32), i64>, start_panel_color: i64) {
+/// The hull painting robot, which reports the color of the panel it's over
+/// and paints and moves as the program directs.
+struct Robot<'a> {
+    hull: &'a mut HashMap<(i32, i32), i64>,
+    position: (i32, i32),
+    facing: (i32, i32),
+    color: Option<i64>,
+}
+
+impl IntcodeIo for Robot<'_> {
+    fn input(&mut self) -> Option<i64> {
+        Some(*self.hull.get(&self.position).unwrap_or(&0))
+    }
+
+    fn output(&mut self, value: i64) {
+        // Outputs alternate between a color and a turn direction.
+        match self.color.take() {
+            None => self.color = Some(value),
+
+            Some(color) => {
+                self.hull.insert(self.position, color);
+
+                self.facing = if value == 0 {
+                    turn_left(self.facing)
+                } else {
+                    turn_right(self.facing)
+                };
+
+                self.position = (
+                    self.position.0 + self.facing.0,
+                    self.position.1 + self.facing.1,
+                );
+            }
+        }
+    }
+}
+
+fn paint_hull(
+    hull: &mut HashMap<(i32, i32), i64>,
+    start_panel_color: i64,
+) -> Result<(), IntcompError> {
     hull.insert((0, 0), start_panel_color);
 
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
-    let mut position = (0, 0);
-    let mut facing = (0, -1); // "Up".
-
-    loop {
-        let input = if hull.contains_key(&position) {
-            *hull.get(&position).unwrap()
-        } else {
-            0
-        };
-
-        intcomp.send_input(input);
-        intcomp.execute();
-
-        if let Some(color) = intcomp.receive_output() {
-            let direction = intcomp
-                .receive_output()
-                .expect("no turn direction supplied");
-
-            hull.insert(position, color);
-
-            facing = if direction == 0 {
-                turn_left(facing)
-            } else {
-                turn_right(facing)
-            };
-
-            position = (position.0 + facing.0, position.1 + facing.1);
-        } else {
-            break;
-        }
-    }
+
+    intcomp.execute_io(&mut Robot {
+        hull,
+        position: (0, 0),
+        facing: (0, -1), // "Up".
+        color: None,
+    })?;
+
+    Ok(())
 }
 
 fn turn_left(facing: (i32, i32)) -> (i32, i32) {
@@ -62,18 +80,20 @@ fn turn_right(facing: (i32, i32)) -> (i32, i32) {
     }
 }
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let mut hull = HashMap::new();
 
-    paint_hull(&mut hull, 0);
+    paint_hull(&mut hull, 0)?;
 
     println!("Total panels painted: {}", hull.len());
+
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let mut hull = HashMap::new();
 
-    paint_hull(&mut hull, 1);
+    paint_hull(&mut hull, 1)?;
 
     let mut min_x = i32::MAX;
     let mut max_x = i32::MIN;
@@ -113,4 +133,6 @@ pub fn part2() {
                 .join("")
         );
     }
+
+    Ok(())
 }
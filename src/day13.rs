@@ -1,4 +1,5 @@
-use crate::intcomp::{read_program, Intcomp};
+use crate::intcomp::io::IntcodeIo;
+use crate::intcomp::{read_program, Intcomp, IntcompError};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
@@ -9,6 +10,7 @@ struct Screen {
     ball: (i64, i64),
     paddle: (i64, i64),
     score: i64,
+    pending: Vec<i64>,
 }
 
 impl Screen {
@@ -22,27 +24,43 @@ impl Screen {
             ball: (-1, -1),
             paddle: (-1, -1),
             score: 0,
+            pending: Vec::new(),
         }
     }
 
-    fn update(&mut self, intcomp: &mut Intcomp) {
-        while let Some(x) = intcomp.receive_output() {
-            let y = intcomp.receive_output().expect("no y coordinate available");
-            let tile_id = intcomp.receive_output().expect("no tile id availble");
+    fn update(&mut self, x: i64, y: i64, tile_id: i64) {
+        if (x, y) == (-1, 0) {
+            self.score = tile_id;
+        } else {
+            let tile = Tile::from_tile_id(tile_id);
 
-            if (x, y) == (-1, 0) {
-                self.score = tile_id;
-            } else {
-                let tile = Tile::from_tile_id(tile_id);
+            if tile == Tile::Ball {
+                self.ball = (x, y);
+            } else if tile == Tile::Paddle {
+                self.paddle = (x, y);
+            }
 
-                if tile == Tile::Ball {
-                    self.ball = (x, y);
-                } else if tile == Tile::Paddle {
-                    self.paddle = (x, y);
-                }
+            self.tiles.insert((x, y), tile);
+        }
+    }
+}
 
-                self.tiles.insert((x, y), tile);
-            }
+/// The screen doubles as the joystick, which always follows the ball.
+impl IntcodeIo for Screen {
+    fn input(&mut self) -> Option<i64> {
+        Some(match self.ball.0.cmp(&self.paddle.0) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+
+    fn output(&mut self, value: i64) {
+        self.pending.push(value);
+
+        if let [x, y, tile_id] = self.pending[..] {
+            self.pending.clear();
+            self.update(x, y, tile_id);
         }
     }
 }
@@ -69,38 +87,31 @@ impl Tile {
     }
 }
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
     let mut screen = Screen::new();
 
-    intcomp.execute();
-    screen.update(&mut intcomp);
+    intcomp.execute_io(&mut screen)?;
 
     let block_count = screen.count_tiles(Tile::Block);
 
     println!("Block tile count: {}", block_count);
+
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
     let mut intcomp = Intcomp::new(&initializer);
     let mut screen = Screen::new();
 
     intcomp.write_memory(0, 2);
-    intcomp.execute();
-    screen.update(&mut intcomp);
 
-    while screen.count_tiles(Tile::Block) > 0 {
-        intcomp.send_input(match screen.ball.0.cmp(&screen.paddle.0) {
-            Ordering::Less => -1,
-            Ordering::Equal => 0,
-            Ordering::Greater => 1,
-        });
-
-        intcomp.execute();
-        screen.update(&mut intcomp);
-    }
+    // The game halts once the last block is broken.
+    intcomp.execute_io(&mut screen)?;
 
     println!("Final score: {}", screen.score);
+
+    Ok(())
 }
@@ -35,7 +35,7 @@ impl Recipe {
         let result = Ingredient::from_string(parts[1]);
         let ingredients = parts[0]
             .split(", ")
-            .map(|ingredient| Ingredient::from_string(ingredient))
+            .map(Ingredient::from_string)
             .collect::<Vec<_>>();
 
         Recipe {
@@ -46,15 +46,15 @@ impl Recipe {
 }
 
 fn count_fuel_made(ore_limit: u64, recipes: &HashMap<String, Recipe>) -> u64 {
-    let single_fuel_cost = get_ore_cost(&recipes, 1);
+    let single_fuel_cost = get_ore_cost(recipes, 1);
 
     let mut min = ore_limit / single_fuel_cost;
     let mut max = 2 * min;
 
     loop {
         let target = (min + max) / 2;
-        let cost = get_ore_cost(&recipes, target);
-        let cost_of_plus_one = get_ore_cost(&recipes, target + 1);
+        let cost = get_ore_cost(recipes, target);
+        let cost_of_plus_one = get_ore_cost(recipes, target + 1);
 
         if cost <= ore_limit && cost_of_plus_one > ore_limit {
             break target;
@@ -72,7 +72,7 @@ fn get_ore_cost(recipes: &HashMap<String, Recipe>, fuel_count: u64) -> u64 {
     let mut elements = HashMap::new();
 
     elements.insert("FUEL".to_string(), fuel_count);
-    elements = reduce_to_ore(&recipes, elements);
+    elements = reduce_to_ore(recipes, elements);
 
     *elements.get(ORE).expect("ore entry not found")
 }
@@ -132,7 +132,7 @@ fn reduce_to_ore(
                     if needed == 0 {
                         Vec::new()
                     } else {
-                        let batches = (needed + recipe.result.count - 1) / recipe.result.count;
+                        let batches = needed.div_ceil(recipe.result.count);
 
                         extras
                             .entry(element.clone())
@@ -1,8 +1,6 @@
-use crate::intcomp::{read_program, Intcomp};
-use std::collections::HashMap;
-use std::i32;
+use crate::intcomp::{read_program, Intcomp, IntcompError};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::slice::Iter;
-use std::u32;
 
 const INPUT_PATH: &str = "day15.input.txt";
 
@@ -45,121 +43,97 @@ impl Direction {
     }
 }
 
-fn find_oxygen_system(intcomp: &mut Intcomp, map: &mut HashMap<(i32, i32), (i64, u32)>) {
-    let mut coords = (0, 0);
-    let mut distance = 0;
+struct Exploration {
+    /// The distance to each open position.
+    distances: HashMap<(i32, i32), u32>,
 
-    loop {
-        let mut next = (Direction::North, (i32::MIN, i32::MIN));
+    /// The oxygen system's distance and a droid stopped there, if it was found.
+    oxygen_system: Option<(u32, Intcomp)>,
+}
+
+/// Map every open position reachable from the droid's current position,
+/// breadth-first.  Rather than walking back and forth, the droid is cloned for
+/// each direction it tries, so every branch continues from its own copy.
+fn explore(droid: Intcomp) -> Result<Exploration, IntcompError> {
+    let mut distances = HashMap::new();
+    let mut walls = HashSet::new();
+    let mut oxygen_system = None;
+    let mut queue = VecDeque::new();
+
+    distances.insert((0, 0), 0);
+    queue.push_back(((0, 0), droid));
+
+    while let Some((coords, droid)) = queue.pop_front() {
+        let distance = distances[&coords] + 1;
 
         for direction in Direction::iter() {
             let delta = direction.get_delta();
             let candidate = (coords.0 + delta.0, coords.1 + delta.1);
 
-            if !map.contains_key(&candidate) {
-                next = (*direction, candidate);
-
-                break;
+            if distances.contains_key(&candidate) || walls.contains(&candidate) {
+                continue;
             }
-        }
 
-        if next.1 == (i32::MIN, i32::MIN) {
-            let mut candidates = Direction::iter()
-                .map(|direction| {
-                    let delta = direction.get_delta();
-                    let candidate = (coords.0 + delta.0, coords.1 + delta.1);
+            let mut next = droid.clone();
 
-                    (
-                        map.get(&candidate)
-                            .expect("CAN'T HAPPEN - candidate not in map")
-                            .1,
-                        (*direction, candidate),
-                    )
-                })
-                .collect::<Vec<_>>();
+            next.send_input(direction.get_input());
+            next.execute()?;
 
-            candidates.sort_by(|&(distance_a, _), (distance_b, _)| distance_a.cmp(distance_b));
-
-            next = candidates[0].1;
-        }
-
-        intcomp.send_input(next.0.get_input());
-        intcomp.execute();
-
-        let candidate = next.1;
-        let status = intcomp.receive_output().expect("no status code received");
+            match next.receive_output().expect("no status code received") {
+                0 => {
+                    walls.insert(candidate);
+                }
 
-        map.entry(candidate)
-            .or_insert((status, if status == 0 { u32::MAX } else { distance + 1 }));
+                status => {
+                    distances.insert(candidate, distance);
 
-        if status != 0 {
-            coords = candidate;
-            distance = map
-                .get(&coords)
-                .expect("CAN'T HAPPEN - coords not in map")
-                .1;
-        }
+                    if status == 2 && oxygen_system.is_none() {
+                        oxygen_system = Some((distance, next.clone()));
+                    }
 
-        if status == 2 {
-            break;
+                    queue.push_back((candidate, next));
+                }
+            }
         }
     }
+
+    Ok(Exploration {
+        distances,
+        oxygen_system,
+    })
 }
 
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
-    let mut intcomp = Intcomp::new(&initializer);
-    let mut map = HashMap::new();
+    let exploration = explore(Intcomp::new(&initializer))?;
+    let (distance, _) = exploration
+        .oxygen_system
+        .expect("CAN'T HAPPEN - no oxygen system found");
 
-    map.insert((0, 0), (1, 0));
+    println!("Found oxygen system in {} steps.", distance);
 
-    find_oxygen_system(&mut intcomp, &mut map);
-
-    println!(
-        "Found oxygen system in {} steps.",
-        map.iter()
-            .filter_map(|(_, (status, distance))| {
-                if *status == 2 {
-                    Some(distance)
-                } else {
-                    None
-                }
-            })
-            .next()
-            .expect("CAN'T HAPPEN - no oxygen system found")
-    );
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
-    let mut intcomp = Intcomp::new(&initializer);
-    let mut map = HashMap::new();
-
-    map.insert((0, 0), (1, 0));
-
-    find_oxygen_system(&mut intcomp, &mut map);
+    let exploration = explore(Intcomp::new(&initializer))?;
+    let (_, droid) = exploration
+        .oxygen_system
+        .expect("CAN'T HAPPEN - no oxygen system found");
 
-    // `intcomp` now controls a robot located at the oxygen system, but we want
-    // to discard the original map and use the oxygen system as the origin
-    // coords.
-    let mut map = HashMap::new();
-
-    map.insert((0, 0), (2, 0));
-
-    // Running the program with the oxygen system as origin maps at least one
-    // cardinal direction.  Running it four times guarantees all cardinal
-    // directions are covered.
-    find_oxygen_system(&mut intcomp, &mut map);
-    find_oxygen_system(&mut intcomp, &mut map);
-    find_oxygen_system(&mut intcomp, &mut map);
-    find_oxygen_system(&mut intcomp, &mut map);
+    // Exploring again from the oxygen system gives the distance the oxygen
+    // has to spread to reach each position.
+    let exploration = explore(droid)?;
 
     println!(
         "Oxygen restored after {} minutes.",
-        (map.iter()
-            .max_by_key(|(_, (status, distance))| if *status == 0 { 0 } else { *distance })
+        exploration
+            .distances
+            .values()
+            .max()
             .expect("CAN'T HAPPEN - no entries in map")
-            .1)
-            .1
     );
+
+    Ok(())
 }
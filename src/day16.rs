@@ -4,7 +4,7 @@ const BASE_PATTERN: [i32; 4] = [0, 1, 0, -1];
 const INPUT_PATH: &str = "day16.input.txt";
 
 fn get_multiplier(scale: usize, offset: usize) -> i32 {
-    BASE_PATTERN[((offset + 1) / (scale + 1) % 4)]
+    BASE_PATTERN[(offset + 1) / (scale + 1) % 4]
 }
 
 fn run_phase(input: &[u32]) -> Vec<u32> {
@@ -1,57 +1,32 @@
-use crate::intcomp::{read_program, Intcomp};
-use std::u8;
+use crate::intcomp::ascii::AsciiIntcomp;
+use crate::intcomp::{read_program, IntcompError};
 
 const INPUT_PATH: &str = "day17.input.txt";
 
-fn print_output(intcomp: &mut Intcomp) {
-    while let Some(code) = intcomp.receive_output() {
-        // If it's too big to be ASCII, it's the final output.
-        if code > u8::MAX as i64 {
-            println!("{}", code);
-        } else {
-            print!("{}", (code as u8) as char);
-        }
-    }
-}
-
-fn send_ascii_line(intcomp: &mut Intcomp, line: &[u8]) {
-    for code in line {
-        intcomp.send_input(*code as i64);
-    }
-
-    intcomp.send_input(10);
-}
-
-pub fn part1() {
+pub fn part1() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
-    let mut intcomp = Intcomp::new(&initializer);
-
-    intcomp.execute();
-
-    let mut output = Vec::new();
-
-    while let Some(code) = intcomp.receive_output() {
-        output.push(code);
-    }
+    let mut ascii = AsciiIntcomp::new(&initializer);
+    let view = ascii.read_until_prompt()?.text;
 
-    let output = output
-        .split(|code| *code == 10)
+    let output = view
+        .lines()
         .filter(|row| !row.is_empty())
+        .map(str::as_bytes)
         .collect::<Vec<_>>();
 
     let mut sum = 0;
 
     for (y, row) in output.iter().enumerate() {
         for (x, code) in row.iter().enumerate() {
-            if *code == 35
+            if *code == b'#'
                 && y > 0
-                && output[y - 1][x] == 35
+                && output[y - 1][x] == b'#'
                 && y < output.len() - 1
-                && output[y + 1][x] == 35
+                && output[y + 1][x] == b'#'
                 && x > 0
-                && output[y][x - 1] == 35
+                && output[y][x - 1] == b'#'
                 && x < row.len() - 1
-                && output[y][x + 1] == 35
+                && output[y][x + 1] == b'#'
             {
                 sum += x * y;
             }
@@ -59,39 +34,34 @@ pub fn part1() {
     }
 
     println!("Sum of alignment parameters: {}", sum);
+
+    Ok(())
 }
 
-pub fn part2() {
+pub fn part2() -> Result<(), IntcompError> {
     let initializer = read_program(INPUT_PATH);
-    let mut intcomp = Intcomp::new(&initializer);
-
-    intcomp.write_memory(0, 2);
-    intcomp.execute();
-
-    print_output(&mut intcomp);
-    send_ascii_line(&mut intcomp, b"A,A,B,C,B,C,B,C,C,A");
-
-    intcomp.execute();
-
-    print_output(&mut intcomp);
-    send_ascii_line(&mut intcomp, b"L,10,R,8,R,8");
-
-    intcomp.execute();
-
-    print_output(&mut intcomp);
-    send_ascii_line(&mut intcomp, b"L,10,L,12,R,8,R,10");
-
-    intcomp.execute();
-
-    print_output(&mut intcomp);
-    send_ascii_line(&mut intcomp, b"R,10,L,12,R,10");
+    let mut ascii = AsciiIntcomp::new(&initializer);
+
+    ascii.intcomp().write_memory(0, 2);
+
+    for line in &[
+        "A,A,B,C,B,C,B,C,C,A",
+        "L,10,R,8,R,8",
+        "L,10,L,12,R,8,R,10",
+        "R,10,L,12,R,10",
+        "n",
+    ] {
+        print!("{}", ascii.read_until_prompt()?.text);
+        ascii.send_line(line);
+    }
 
-    intcomp.execute();
+    let output = ascii.read_until_prompt()?;
 
-    print_output(&mut intcomp);
-    send_ascii_line(&mut intcomp, b"n");
+    print!("{}", output.text);
 
-    intcomp.execute();
+    for value in output.values {
+        println!("{}", value);
+    }
 
-    print_output(&mut intcomp);
+    Ok(())
 }
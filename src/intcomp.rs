@@ -1,8 +1,44 @@
+pub mod analysis;
+pub mod ascii;
+pub mod assembler;
+pub mod asynchronous;
+#[doc(hidden)]
+pub mod bench;
+pub mod compiler;
+pub mod debugger;
+pub mod dialect;
+pub mod disassembler;
+#[cfg(test)]
+mod fixtures;
+pub mod inspect;
+pub mod io;
+pub mod loader;
+pub mod memory;
+pub mod network;
+pub mod pipeline;
+pub mod profiler;
+#[cfg(test)]
+mod properties;
+pub mod snapshot;
+pub mod solver;
+pub mod trace;
+mod varint;
+pub mod word;
+
+use dialect::{CustomOpcode, Decoded, Dialect, Effect, OperandKind};
+use io::{IntcodeIo, Queues};
+use memory::{DenseMemory, Memory};
 use std::collections::VecDeque;
-use std::fs;
-use std::str;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, OnceLock};
+use word::Word;
 
-#[derive(Debug, PartialEq)]
+/// One more than the largest instruction with three operands, all relative.
+const DECODED_WORDS: i64 = 22_300;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Instruction {
     Add(OperandMode, OperandMode, OperandMode),
     Multiply(OperandMode, OperandMode, OperandMode),
@@ -17,276 +53,831 @@ enum Instruction {
 }
 
 impl Instruction {
-    fn parse(instruction: i64) -> Instruction {
+    /// `address` is only used to report where a malformed instruction was found.
+    fn parse(instruction: i64, address: usize) -> Result<Instruction, IntcompError> {
         let opcode = instruction % 100;
 
-        match opcode {
-            1 => Instruction::Add(
-                OperandMode::from_digit(get_digit(instruction, 3)),
-                OperandMode::from_digit(get_digit(instruction, 4)),
-                OperandMode::from_digit(get_digit(instruction, 5)),
-            ),
+        let mode = |digit| {
+            let digit = get_digit(instruction, digit);
+
+            OperandMode::from_digit(digit).ok_or(IntcompError::InvalidMode { address, digit })
+        };
+
+        let instruction = match opcode {
+            1 => Instruction::Add(mode(3)?, mode(4)?, mode(5)?),
+            2 => Instruction::Multiply(mode(3)?, mode(4)?, mode(5)?),
+            3 => Instruction::Input(mode(3)?),
+            4 => Instruction::Output(mode(3)?),
+            5 => Instruction::JumpIfTrue(mode(3)?, mode(4)?),
+            6 => Instruction::JumpIfFalse(mode(3)?, mode(4)?),
+            7 => Instruction::LessThan(mode(3)?, mode(4)?, mode(5)?),
+            8 => Instruction::Equals(mode(3)?, mode(4)?, mode(5)?),
+            9 => Instruction::AdjustRelativeBase(mode(3)?),
+            99 => Instruction::Halt,
+
+            _ => {
+                return Err(IntcompError::InvalidOpcode {
+                    address,
+                    opcode: instruction,
+                })
+            }
+        };
+
+        Ok(instruction)
+    }
+
+    /// As `parse`, but using a table of every instruction without extra digits
+    /// decoded in advance.  Decoding depends only on the word itself, so the
+    /// table stays correct even when a program rewrites its own code.
+    fn decode(instruction: i64, address: usize) -> Result<Instruction, IntcompError> {
+        static DECODED: OnceLock<Vec<Option<Instruction>>> = OnceLock::new();
+
+        let decoded = DECODED.get_or_init(|| {
+            (0..DECODED_WORDS)
+                .map(|word| Instruction::parse(word, 0).ok())
+                .collect()
+        });
+
+        match decoded.get(instruction as usize) {
+            Some(Some(decoded)) if instruction >= 0 => Ok(*decoded),
+            _ => Instruction::parse(instruction, address),
+        }
+    }
+
+    /// The number of words the instruction occupies, including the opcode.
+    fn width(&self) -> usize {
+        match self {
+            Instruction::Add(_, _, _)
+            | Instruction::Multiply(_, _, _)
+            | Instruction::LessThan(_, _, _)
+            | Instruction::Equals(_, _, _) => 4,
+
+            Instruction::JumpIfTrue(_, _) | Instruction::JumpIfFalse(_, _) => 3,
+
+            Instruction::Input(_) | Instruction::Output(_) | Instruction::AdjustRelativeBase(_) => {
+                2
+            }
+
+            Instruction::Halt => 1,
+        }
+    }
+}
+
+/// Everything which can go wrong while running an Intcode program.  Each
+/// variant records the address of the instruction which caused the problem.
+#[derive(Debug, PartialEq)]
+pub enum IntcompError {
+    InvalidOpcode {
+        address: usize,
+        opcode: i64,
+    },
+    InvalidMode {
+        address: usize,
+        digit: u8,
+    },
+    ImmediateWrite {
+        address: usize,
+    },
+    NegativeAddress {
+        address: usize,
+        target: i64,
+    },
+    IpOutOfBounds {
+        address: usize,
+    },
+    AddressLimitExceeded {
+        address: usize,
+        target: i64,
+    },
+    Overflow {
+        address: usize,
+    },
+    InstructionLimitExceeded {
+        address: usize,
+    },
+    OutputLimitExceeded {
+        address: usize,
+    },
+
+    /// A custom instruction gave a different number of results than it has
+    /// targets.
+    WrongResultCount {
+        address: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for IntcompError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcompError::InvalidOpcode { address, opcode } => {
+                write!(f, "invalid opcode {} at address {}", opcode, address)
+            }
+
+            IntcompError::InvalidMode { address, digit } => {
+                write!(f, "invalid operand mode {} at address {}", digit, address)
+            }
 
-            2 => Instruction::Multiply(
-                OperandMode::from_digit(get_digit(instruction, 3)),
-                OperandMode::from_digit(get_digit(instruction, 4)),
-                OperandMode::from_digit(get_digit(instruction, 5)),
+            IntcompError::ImmediateWrite { address } => {
+                write!(
+                    f,
+                    "cannot write to immediate operand at address {}",
+                    address
+                )
+            }
+
+            IntcompError::NegativeAddress { address, target } => write!(
+                f,
+                "instruction at address {} referenced negative address {}",
+                address, target
             ),
 
-            3 => Instruction::Input(OperandMode::from_digit(get_digit(instruction, 3))),
-            4 => Instruction::Output(OperandMode::from_digit(get_digit(instruction, 3))),
+            IntcompError::IpOutOfBounds { address } => {
+                write!(f, "instruction pointer {} is out of bounds", address)
+            }
 
-            5 => Instruction::JumpIfTrue(
-                OperandMode::from_digit(get_digit(instruction, 3)),
-                OperandMode::from_digit(get_digit(instruction, 4)),
+            IntcompError::AddressLimitExceeded { address, target } => write!(
+                f,
+                "instruction at address {} referenced address {}, beyond the memory limit",
+                address, target
             ),
 
-            6 => Instruction::JumpIfFalse(
-                OperandMode::from_digit(get_digit(instruction, 3)),
-                OperandMode::from_digit(get_digit(instruction, 4)),
+            IntcompError::Overflow { address } => {
+                write!(f, "arithmetic overflow at address {}", address)
+            }
+
+            IntcompError::InstructionLimitExceeded { address } => write!(
+                f,
+                "instruction limit reached before executing address {}",
+                address
             ),
 
-            7 => Instruction::LessThan(
-                OperandMode::from_digit(get_digit(instruction, 3)),
-                OperandMode::from_digit(get_digit(instruction, 4)),
-                OperandMode::from_digit(get_digit(instruction, 5)),
+            IntcompError::OutputLimitExceeded { address } => write!(
+                f,
+                "output at address {} would exceed the output limit",
+                address
             ),
 
-            8 => Instruction::Equals(
-                OperandMode::from_digit(get_digit(instruction, 3)),
-                OperandMode::from_digit(get_digit(instruction, 4)),
-                OperandMode::from_digit(get_digit(instruction, 5)),
+            IntcompError::WrongResultCount {
+                address,
+                expected,
+                found,
+            } => write!(
+                f,
+                "instruction at address {} gave {} results for {} targets",
+                address, found, expected
             ),
+        }
+    }
+}
 
-            9 => {
-                Instruction::AdjustRelativeBase(OperandMode::from_digit(get_digit(instruction, 3)))
-            }
+impl Error for IntcompError {}
 
-            99 => Instruction::Halt,
-            _ => panic!("Invalid opcode! ({})", opcode),
-        }
+/// Why `Intcomp` stopped executing and returned control to the caller.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunState {
+    /// The program reached a `Halt` instruction.
+    Halted,
+
+    /// The program reached an `Input` instruction with no input queued.
+    AwaitingInput,
+
+    /// The program executed an `Output` instruction.
+    OutputProduced,
+
+    /// The program executed as many instructions as it was allowed to.
+    StepLimitReached,
+
+    /// A `Hook` stopped execution before the instruction at `ip` took effect.
+    Interrupted,
+}
+
+/// Callbacks which let tools such as the debugger observe and interrupt a
+/// running program.  Returning `false` from either method stops execution
+/// before the current instruction has any effect, so that running again
+/// retries the same instruction.  `W` is the machine's word type.
+pub trait Hook<W = i64> {
+    /// Called before the instruction at `ip` is decoded.
+    fn before_instruction(&mut self, _ip: usize, _bp: W) -> bool {
+        true
+    }
+
+    /// Called after an operand's `value` is read from `address`.
+    fn after_read(&mut self, _address: usize, _value: W) {}
+
+    /// Called before `value` is written to `address`.
+    fn before_write(&mut self, _address: usize, _value: W) -> bool {
+        true
     }
+
+    /// Called once the instruction which was found at `ip` has taken effect.
+    /// `instruction` is its undecoded value, as it was before it ran.
+    fn after_instruction(&mut self, _ip: usize, _instruction: W) {}
 }
 
-pub struct Intcomp {
-    memory: Vec<i64>,
+/// The hook used by the plain execution methods, which never interrupts.
+pub struct NoHook;
+
+impl<W> Hook<W> for NoHook {}
+
+#[derive(Clone)]
+pub struct Intcomp<M: Memory = DenseMemory> {
+    memory: M,
+    memory_limit: Option<usize>,
+    instruction_limit: Option<u64>,
+    output_limit: Option<usize>,
+    executed: u64,
+
+    /// Outputs produced over every run, for the output limit when the
+    /// outputs aren't queued.
+    produced: usize,
+
+    dialect: Option<Arc<Dialect>>,
     ip: usize,
-    bp: usize,
-    input: VecDeque<i64>,
-    output: VecDeque<i64>,
+    bp: M::Word,
+    input: VecDeque<M::Word>,
+    output: VecDeque<M::Word>,
 }
 
-impl Intcomp {
-    pub fn execute(&mut self) {
-        loop {
-            let instruction = Instruction::parse(self.memory[self.ip]);
+impl<M: Memory> Intcomp<M> {
+    /// Run until the program halts or needs input which hasn't been sent yet.
+    pub fn execute(&mut self) -> Result<RunState, IntcompError> {
+        self.run_queued(None, false, &mut NoHook)
+    }
 
-            match instruction {
-                Instruction::Add(operand1_mode, operand2_mode, target_mode) => {
-                    let operand1 = self.get_value(operand1_mode, self.ip + 1);
-                    let operand2 = self.get_value(operand2_mode, self.ip + 2);
-                    let target = self.get_target(target_mode, self.ip + 3);
+    /// Run as `execute` does, taking input from `io` whenever the input queue
+    /// is empty and sending all output to it.
+    pub fn execute_io<IO: IntcodeIo<M::Word>>(
+        &mut self,
+        io: &mut IO,
+    ) -> Result<RunState, IntcompError> {
+        self.run(None, false, &mut NoHook, io)
+    }
 
-                    self.extend_memory(target);
-                    self.memory[target] = operand1 + operand2;
-                    self.ip += 4;
-                }
+    /// Run as `execute` does, reporting to `hook` along the way.
+    pub fn execute_with<H: Hook<M::Word>>(
+        &mut self,
+        hook: &mut H,
+    ) -> Result<RunState, IntcompError> {
+        self.run_queued(None, false, hook)
+    }
 
-                Instruction::Multiply(operand1_mode, operand2_mode, target_mode) => {
-                    let operand1 = self.get_value(operand1_mode, self.ip + 1);
-                    let operand2 = self.get_value(operand2_mode, self.ip + 2);
-                    let target = self.get_target(target_mode, self.ip + 3);
+    /// Run as `execute_io` does, reporting to `hook` along the way.
+    pub fn execute_with_io<H: Hook<M::Word>, IO: IntcodeIo<M::Word>>(
+        &mut self,
+        hook: &mut H,
+        io: &mut IO,
+    ) -> Result<RunState, IntcompError> {
+        self.run(None, false, hook, io)
+    }
 
-                    self.extend_memory(target);
-                    self.memory[target] = operand1 * operand2;
-                    self.ip += 4;
-                }
+    /// Execute a single instruction, returning `StepLimitReached` if it didn't
+    /// halt, await input or produce output.
+    pub fn step(&mut self) -> Result<RunState, IntcompError> {
+        self.run_queued(Some(1), true, &mut NoHook)
+    }
 
-                Instruction::Input(target_mode) => {
-                    let target = self.get_target(target_mode, self.ip + 1);
+    /// Run until the program produces a single output, halts or needs input.
+    pub fn run_until_output(&mut self) -> Result<RunState, IntcompError> {
+        self.run_queued(None, true, &mut NoHook)
+    }
 
-                    let input = match self.input.pop_front() {
-                        Some(input) => input,
+    /// Run as `execute` does, but stop after at most `limit` instructions.
+    pub fn run_for(&mut self, limit: usize) -> Result<RunState, IntcompError> {
+        self.run_queued(Some(limit), false, &mut NoHook)
+    }
 
-                        // Terminate and await input.
-                        None => return,
-                    };
+    /// Run as `run_for` does, reporting to `hook` along the way.
+    pub fn run_for_with<H: Hook<M::Word>>(
+        &mut self,
+        limit: usize,
+        hook: &mut H,
+    ) -> Result<RunState, IntcompError> {
+        self.run_queued(Some(limit), false, hook)
+    }
 
-                    self.extend_memory(target);
-                    self.memory[target] = input;
-                    self.ip += 2;
-                }
+    /// Run with the machine's own output queue as its I/O.
+    fn run_queued<H: Hook<M::Word>>(
+        &mut self,
+        limit: Option<usize>,
+        stop_on_output: bool,
+        hook: &mut H,
+    ) -> Result<RunState, IntcompError> {
+        let mut queues = Queues {
+            input: VecDeque::new(),
+            output: mem::take(&mut self.output),
+        };
 
-                Instruction::Output(output_mode) => {
-                    let output = self.get_value(output_mode, self.ip + 1);
+        let result = self.run(limit, stop_on_output, hook, &mut queues);
 
-                    self.output.push_back(output);
-                    self.ip += 2;
-                }
+        self.output = queues.output;
 
-                Instruction::JumpIfTrue(operand_mode, jump_to_mode) => {
-                    let operand = self.get_value(operand_mode, self.ip + 1);
-                    let jump_to = self.get_value(jump_to_mode, self.ip + 2);
+        result
+    }
 
-                    if operand != 0 {
-                        self.ip = jump_to as usize;
-                    } else {
-                        self.ip += 3;
-                    }
-                }
+    fn run<H: Hook<M::Word>, IO: IntcodeIo<M::Word>>(
+        &mut self,
+        mut limit: Option<usize>,
+        stop_on_output: bool,
+        hook: &mut H,
+        io: &mut IO,
+    ) -> Result<RunState, IntcompError> {
+        loop {
+            if limit == Some(0) {
+                return Ok(RunState::StepLimitReached);
+            }
 
-                Instruction::JumpIfFalse(operand_mode, jump_to_mode) => {
-                    let operand = self.get_value(operand_mode, self.ip + 1);
-                    let jump_to = self.get_value(jump_to_mode, self.ip + 2);
+            match self.execute_instruction(hook, io)? {
+                Some(RunState::OutputProduced) if !stop_on_output => {}
+                Some(state) => return Ok(state),
+                None => {}
+            }
 
-                    if operand == 0 {
-                        self.ip = jump_to as usize;
-                    } else {
-                        self.ip += 3;
-                    }
-                }
+            limit = limit.map(|limit| limit - 1);
+        }
+    }
 
-                Instruction::LessThan(operand1_mode, operand2_mode, target_mode) => {
-                    let operand1 = self.get_value(operand1_mode, self.ip + 1);
-                    let operand2 = self.get_value(operand2_mode, self.ip + 2);
-                    let target = self.get_target(target_mode, self.ip + 3);
+    /// Execute the instruction at `ip`.  Returns the resulting state if the
+    /// caller might want to stop running, or `None` to carry on.
+    fn execute_instruction<H: Hook<M::Word>, IO: IntcodeIo<M::Word>>(
+        &mut self,
+        hook: &mut H,
+        io: &mut IO,
+    ) -> Result<Option<RunState>, IntcompError> {
+        if !hook.before_instruction(self.ip, self.bp) {
+            return Ok(Some(RunState::Interrupted));
+        }
 
-                    self.extend_memory(target);
-                    self.memory[target] = if operand1 < operand2 { 1 } else { 0 };
-                    self.ip += 4;
-                }
+        let ip = self.ip;
 
-                Instruction::Equals(operand1_mode, operand2_mode, target_mode) => {
-                    let operand1 = self.get_value(operand1_mode, self.ip + 1);
-                    let operand2 = self.get_value(operand2_mode, self.ip + 2);
-                    let target = self.get_target(target_mode, self.ip + 3);
+        if self
+            .instruction_limit
+            .is_some_and(|limit| self.executed >= limit)
+        {
+            return Err(IntcompError::InstructionLimitExceeded { address: ip });
+        }
 
-                    self.extend_memory(target);
-                    self.memory[target] = if operand1 == operand2 { 1 } else { 0 };
-                    self.ip += 4;
-                }
+        if ip >= self.memory.len() {
+            return Err(IntcompError::IpOutOfBounds { address: ip });
+        }
 
-                Instruction::AdjustRelativeBase(operand_mode) => {
-                    let operand = self.get_value(operand_mode, self.ip + 1);
+        let word = self.memory.read(ip);
 
-                    if operand < 0 {
-                        let operand = i64::abs(operand);
+        let state = match self.dialect.clone() {
+            None => {
+                let instruction = self.decode(ip, word)?;
+                self.perform(instruction, hook, io)?
+            }
 
-                        self.bp -= operand as usize
-                    } else {
-                        self.bp += operand as usize
-                    }
+            Some(dialect) => {
+                let instruction = match word.to_i64() {
+                    Some(instruction) => instruction,
+                    None => return Err(IntcompError::Overflow { address: ip }),
+                };
 
-                    self.ip += 2;
+                match dialect.decode(instruction, ip)? {
+                    Decoded::Builtin(instruction) => self.perform(instruction, hook, io)?,
+                    Decoded::Custom(custom) => self.perform_custom(instruction, custom, hook)?,
                 }
+            }
+        };
+
+        match state {
+            Some(RunState::AwaitingInput) | Some(RunState::Interrupted) => {}
 
-                Instruction::Halt => return,
+            _ => {
+                self.executed += 1;
+                hook.after_instruction(ip, word);
             }
         }
+
+        Ok(state)
     }
 
-    fn extend_memory(&mut self, address: usize) {
-        if address >= self.memory.len() {
-            self.memory.resize_with(address + 1, Default::default);
+    /// Decode `word`, which was found at `ip`.
+    fn decode(&self, ip: usize, word: M::Word) -> Result<Instruction, IntcompError> {
+        match word.to_i64() {
+            Some(instruction) => Instruction::decode(instruction, ip),
+            None => Err(IntcompError::Overflow { address: ip }),
         }
     }
 
-    /// get_target differs from get_value in that targets cannot be in Immediate mode.
-    fn get_target(&mut self, operand_mode: OperandMode, operand_address: usize) -> usize {
-        match operand_mode {
-            OperandMode::Position => {
-                let address = self.memory[operand_address] as usize;
+    fn perform<H: Hook<M::Word>, IO: IntcodeIo<M::Word>>(
+        &mut self,
+        instruction: Instruction,
+        hook: &mut H,
+        io: &mut IO,
+    ) -> Result<Option<RunState>, IntcompError> {
+        match instruction {
+            Instruction::Add(operand1_mode, operand2_mode, target_mode) => {
+                let operand1 = self.get_value(hook, operand1_mode, self.ip + 1)?;
+                let operand2 = self.get_value(hook, operand2_mode, self.ip + 2)?;
+                let target = self.get_target(target_mode, self.ip + 3)?;
+
+                return Ok(self.write(
+                    hook,
+                    target,
+                    self.checked(operand1.checked_add(operand2))?,
+                    4,
+                ));
+            }
 
-                self.extend_memory(address);
+            Instruction::Multiply(operand1_mode, operand2_mode, target_mode) => {
+                let operand1 = self.get_value(hook, operand1_mode, self.ip + 1)?;
+                let operand2 = self.get_value(hook, operand2_mode, self.ip + 2)?;
+                let target = self.get_target(target_mode, self.ip + 3)?;
+
+                return Ok(self.write(
+                    hook,
+                    target,
+                    self.checked(operand1.checked_mul(operand2))?,
+                    4,
+                ));
+            }
 
-                address
+            Instruction::Input(target_mode) => {
+                let target = self.get_target(target_mode, self.ip + 1)?;
+
+                // Inputs from `io` are queued so that an interrupted write
+                // can be retried.
+                let input = match self.input.front() {
+                    Some(input) => *input,
+
+                    None => match io.input() {
+                        Some(input) => {
+                            self.input.push_back(input);
+                            input
+                        }
+
+                        // Terminate and await input.
+                        None => return Ok(Some(RunState::AwaitingInput)),
+                    },
+                };
+
+                let state = self.write(hook, target, input, 2);
+
+                if state.is_none() {
+                    self.input.pop_front();
+                }
+
+                return Ok(state);
             }
 
-            OperandMode::Immediate => panic!("cannot write to immediate operand"),
+            Instruction::Output(output_mode) => {
+                let output = self.get_value(hook, output_mode, self.ip + 1)?;
 
-            OperandMode::Relative => {
-                let operand = self.memory[operand_address];
+                let unread = io.queued_outputs().unwrap_or(self.produced);
+
+                if self.output_limit.is_some_and(|limit| unread >= limit) {
+                    return Err(IntcompError::OutputLimitExceeded { address: self.ip });
+                }
+
+                io.output(output);
+                self.produced += 1;
+                self.ip += 2;
 
-                let address = if operand < 0 {
-                    let operand = i64::abs(operand);
+                return Ok(Some(RunState::OutputProduced));
+            }
+
+            Instruction::JumpIfTrue(operand_mode, jump_to_mode) => {
+                let operand = self.get_value(hook, operand_mode, self.ip + 1)?;
+                let jump_to = self.get_value(hook, jump_to_mode, self.ip + 2)?;
 
-                    self.bp - operand as usize
+                if operand != M::Word::default() {
+                    self.ip = self.check_address(jump_to)?;
                 } else {
-                    self.bp + operand as usize
-                };
+                    self.ip += 3;
+                }
+            }
 
-                self.extend_memory(address);
+            Instruction::JumpIfFalse(operand_mode, jump_to_mode) => {
+                let operand = self.get_value(hook, operand_mode, self.ip + 1)?;
+                let jump_to = self.get_value(hook, jump_to_mode, self.ip + 2)?;
 
-                address
+                if operand == M::Word::default() {
+                    self.ip = self.check_address(jump_to)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+
+            Instruction::LessThan(operand1_mode, operand2_mode, target_mode) => {
+                let operand1 = self.get_value(hook, operand1_mode, self.ip + 1)?;
+                let operand2 = self.get_value(hook, operand2_mode, self.ip + 2)?;
+                let target = self.get_target(target_mode, self.ip + 3)?;
+
+                return Ok(self.write(
+                    hook,
+                    target,
+                    M::Word::from(if operand1 < operand2 { 1 } else { 0 }),
+                    4,
+                ));
+            }
+
+            Instruction::Equals(operand1_mode, operand2_mode, target_mode) => {
+                let operand1 = self.get_value(hook, operand1_mode, self.ip + 1)?;
+                let operand2 = self.get_value(hook, operand2_mode, self.ip + 2)?;
+                let target = self.get_target(target_mode, self.ip + 3)?;
+
+                return Ok(self.write(
+                    hook,
+                    target,
+                    M::Word::from(if operand1 == operand2 { 1 } else { 0 }),
+                    4,
+                ));
+            }
+
+            Instruction::AdjustRelativeBase(operand_mode) => {
+                let operand = self.get_value(hook, operand_mode, self.ip + 1)?;
+
+                self.bp = self.checked(self.bp.checked_add(operand))?;
+                self.ip += 2;
             }
+
+            Instruction::Halt => return Ok(Some(RunState::Halted)),
         }
+
+        Ok(None)
     }
 
-    fn get_value(&mut self, operand_mode: OperandMode, operand_address: usize) -> i64 {
-        match operand_mode {
-            OperandMode::Position => {
-                let address = self.memory[operand_address] as usize;
+    /// Execute an instruction from the machine's dialect which isn't built in.
+    fn perform_custom<H: Hook<M::Word>>(
+        &mut self,
+        instruction: i64,
+        custom: &CustomOpcode,
+        hook: &mut H,
+    ) -> Result<Option<RunState>, IntcompError> {
+        let mut values = Vec::new();
+        let mut targets = Vec::new();
+
+        for (index, kind) in custom.operands.iter().enumerate() {
+            let digit = ((instruction / 10_i64.pow(index as u32 + 2)) % 10) as u8;
+            let address = self.ip + 1 + index;
+
+            let mode = OperandMode::from_digit(digit).ok_or(IntcompError::InvalidMode {
+                address: self.ip,
+                digit,
+            })?;
+
+            match kind {
+                OperandKind::Value => {
+                    let value = self.get_value(hook, mode, address)?;
+
+                    match value.to_i64() {
+                        Some(value) => values.push(value),
+                        None => return Err(IntcompError::Overflow { address: self.ip }),
+                    }
+                }
+
+                OperandKind::Target => targets.push(self.get_target(mode, address)?),
+            }
+        }
+
+        match custom.apply(&values) {
+            Effect::Continue(results) => {
+                if results.len() != custom.target_count() {
+                    return Err(IntcompError::WrongResultCount {
+                        address: self.ip,
+                        expected: custom.target_count(),
+                        found: results.len(),
+                    });
+                }
 
-                self.extend_memory(address);
+                // Every write is offered to the hook before any takes effect,
+                // so an interrupted instruction can be retried.
+                for (target, value) in targets.iter().zip(&results) {
+                    if !hook.before_write(*target, M::Word::from(*value)) {
+                        return Ok(Some(RunState::Interrupted));
+                    }
+                }
 
-                self.memory[address]
+                for (target, value) in targets.into_iter().zip(results) {
+                    self.memory.write(target, M::Word::from(value));
+                }
+
+                self.ip += 1 + custom.operands.len();
             }
 
-            OperandMode::Immediate => self.memory[operand_address],
+            Effect::Jump(target) => self.ip = self.check_address(M::Word::from(target))?,
+            Effect::Halt => return Ok(Some(RunState::Halted)),
+        }
 
-            OperandMode::Relative => {
-                let operand = self.memory[operand_address];
+        Ok(None)
+    }
+
+    /// Write `value` to `target` and move past the current instruction, which is
+    /// `width` words long, unless the hook interrupts first.
+    fn write<H: Hook<M::Word>>(
+        &mut self,
+        hook: &mut H,
+        target: usize,
+        value: M::Word,
+        width: usize,
+    ) -> Option<RunState> {
+        if !hook.before_write(target, value) {
+            return Some(RunState::Interrupted);
+        }
 
-                let address = if operand < 0 {
-                    let operand = i64::abs(operand);
+        self.memory.write(target, value);
+        self.ip += width;
 
-                    self.bp - operand as usize
-                } else {
-                    self.bp + operand as usize
-                };
+        None
+    }
+
+    /// Turn the result of checked arithmetic into an error if it overflowed.
+    fn checked(&self, value: Option<M::Word>) -> Result<M::Word, IntcompError> {
+        value.ok_or(IntcompError::Overflow { address: self.ip })
+    }
+
+    /// Convert a computed address into an index, rejecting negative addresses
+    /// and any beyond the memory limit.
+    fn check_address(&self, address: M::Word) -> Result<usize, IntcompError> {
+        let address = match address.to_i64() {
+            Some(address) => address,
+            None => return Err(IntcompError::Overflow { address: self.ip }),
+        };
+
+        if address < 0 {
+            return Err(IntcompError::NegativeAddress {
+                address: self.ip,
+                target: address,
+            });
+        }
+
+        match self.memory_limit {
+            Some(limit) if address as usize >= limit => Err(IntcompError::AddressLimitExceeded {
+                address: self.ip,
+                target: address,
+            }),
+
+            _ => Ok(address as usize),
+        }
+    }
 
-                self.extend_memory(address);
+    /// Resolve the address an operand refers to.
+    fn get_address(
+        &self,
+        operand_mode: OperandMode,
+        operand_address: usize,
+    ) -> Result<usize, IntcompError> {
+        let operand = self.memory.read(operand_address);
 
-                self.memory[address]
+        match operand_mode {
+            OperandMode::Position => self.check_address(operand),
+            OperandMode::Immediate => Ok(operand_address),
+            OperandMode::Relative => {
+                self.check_address(self.checked(self.bp.checked_add(operand))?)
             }
         }
     }
 
+    /// get_target differs from get_value in that targets cannot be in Immediate mode.
+    fn get_target(
+        &self,
+        operand_mode: OperandMode,
+        operand_address: usize,
+    ) -> Result<usize, IntcompError> {
+        if operand_mode == OperandMode::Immediate {
+            return Err(IntcompError::ImmediateWrite { address: self.ip });
+        }
+
+        self.get_address(operand_mode, operand_address)
+    }
+
+    fn get_value<H: Hook<M::Word>>(
+        &self,
+        hook: &mut H,
+        operand_mode: OperandMode,
+        operand_address: usize,
+    ) -> Result<M::Word, IntcompError> {
+        let address = self.get_address(operand_mode, operand_address)?;
+        let value = self.memory.read(address);
+
+        hook.after_read(address, value);
+
+        Ok(value)
+    }
+
     pub fn is_halted(&self) -> bool {
-        match Instruction::parse(self.memory[self.ip]) {
-            Instruction::Halt => true,
-            _ => false,
+        match self.memory.read(self.ip).to_i64() {
+            Some(instruction) => {
+                matches!(
+                    Instruction::parse(instruction, self.ip),
+                    Ok(Instruction::Halt)
+                )
+            }
+
+            None => false,
         }
     }
 
-    pub fn new(intitial_memory: &[i64]) -> Intcomp {
+    /// Create a machine which uses `memory`, already holding the program, as
+    /// its storage.
+    pub fn with_memory(memory: M) -> Intcomp<M> {
         Intcomp {
-            memory: intitial_memory.to_vec(),
+            memory,
+            memory_limit: None,
+            instruction_limit: None,
+            output_limit: None,
+            executed: 0,
+            produced: 0,
+            dialect: None,
             ip: 0,
-            bp: 0,
+            bp: M::Word::default(),
             input: VecDeque::new(),
             output: VecDeque::new(),
         }
     }
 
-    pub fn read_memory(&self, address: usize) -> i64 {
-        self.memory[address]
+    /// Make any instruction which refers to `limit` or beyond fail with
+    /// `AddressLimitExceeded` instead of allocating memory.  Since nothing at
+    /// or beyond `limit` is ever written, this also bounds the memory the
+    /// machine holds, to `limit` words for `DenseMemory` and the pages
+    /// covering them for `PagedMemory`.  `None` removes the limit.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    /// Fail with `InstructionLimitExceeded` instead of executing any more once
+    /// `limit` instructions have been executed in total, over every run of
+    /// this machine.  Unlike `run_for`, the budget isn't reset between calls.
+    /// `None` removes the limit.
+    pub fn set_instruction_limit(&mut self, limit: Option<u64>) {
+        self.instruction_limit = limit;
+    }
+
+    /// Fail with `OutputLimitExceeded` instead of producing an output while
+    /// `limit` outputs are already queued and not yet received.  Outputs sent
+    /// to an `IntcodeIo` which doesn't queue them, such as a `Tape`, are never
+    /// received as far as the machine knows, so there the limit is on every
+    /// output produced over every run, like the instruction limit.  `None`
+    /// removes the limit.
+    pub fn set_output_limit(&mut self, limit: Option<usize>) {
+        self.output_limit = limit;
+    }
+
+    /// Understand only the opcodes in `dialect`, rather than every built-in
+    /// one.
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = Some(Arc::new(dialect));
+    }
+
+    /// The number of instructions executed so far, over every run.
+    pub fn instructions_executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// Create a machine with memory of type `M` holding `program`, converting
+    /// each word as necessary.
+    pub fn load(program: &[i64]) -> Intcomp<M> {
+        let words = program
+            .iter()
+            .map(|word| M::Word::from(*word))
+            .collect::<Vec<_>>();
+
+        Intcomp::with_memory(M::load(&words))
+    }
+
+    pub fn read_memory(&self, address: usize) -> M::Word {
+        self.memory.read(address)
+    }
+
+    /// As `read_memory`, but `None` beyond the highest address which has
+    /// been loaded or written, rather than zero.
+    pub fn try_read_memory(&self, address: usize) -> Option<M::Word> {
+        if address < self.memory.len() {
+            Some(self.memory.read(address))
+        } else {
+            None
+        }
+    }
+
+    /// One more than the highest address which has been loaded or written.
+    pub fn memory_len(&self) -> usize {
+        self.memory.len()
     }
 
-    pub fn receive_output(&mut self) -> Option<i64> {
+    pub fn receive_output(&mut self) -> Option<M::Word> {
         self.output.pop_front()
     }
 
-    pub fn send_input(&mut self, input: i64) {
+    pub fn send_input(&mut self, input: M::Word) {
         self.input.push_back(input);
     }
 
-    pub fn write_memory(&mut self, address: usize, value: i64) {
-        self.memory[address] = value;
+    pub fn write_memory(&mut self, address: usize, value: M::Word) {
+        self.memory.write(address, value);
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl Intcomp {
+    pub fn new(intitial_memory: &[i64]) -> Intcomp {
+        Intcomp::with_memory(DenseMemory::load(intitial_memory))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum OperandMode {
     Position,
     Immediate,
@@ -294,38 +885,40 @@ enum OperandMode {
 }
 
 impl OperandMode {
-    fn from_digit(digit: u8) -> OperandMode {
+    fn from_digit(digit: u8) -> Option<OperandMode> {
         match digit {
-            0 => OperandMode::Position,
-            1 => OperandMode::Immediate,
-            2 => OperandMode::Relative,
-            _ => panic!("Invalid address type! ({})", digit),
+            0 => Some(OperandMode::Position),
+            1 => Some(OperandMode::Immediate),
+            2 => Some(OperandMode::Relative),
+            _ => None,
         }
     }
 }
 
-/// Get the nth digit from the right.
-fn get_digit(number: i64, digit: u32) -> u8 {
-    let base = i64::pow(10, digit - 1);
+const POWERS_OF_TEN: [i64; 5] = [1, 10, 100, 1_000, 10_000];
 
-    ((number / base) % 10) as u8
+/// Get the nth digit from the right.  Instructions have at most five digits,
+/// so that's all this supports.
+fn get_digit(number: i64, digit: usize) -> u8 {
+    ((number / POWERS_OF_TEN[digit - 1]) % 10) as u8
 }
 
+/// Load the program at `path`, in any form `loader::load_program` accepts.
+/// Panics if it can't be loaded, which is fine for puzzle inputs; use the
+/// loader directly to handle errors.
 pub fn read_program(path: &str) -> Vec<i64> {
-    fs::read(path)
-        .expect("could not read program file")
-        .split(|byte| byte == &b',')
-        .map(|number| str::from_utf8(&number).unwrap().parse::<i64>().unwrap())
-        .collect::<Vec<_>>()
+    loader::load_program(path).unwrap_or_else(|error| panic!("could not load {}: {}", path, error))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::io::Tape;
+    use super::memory::PagedMemory;
     use super::*;
 
     #[test]
     fn instruction_parse_supports_add() {
-        match Instruction::parse(1) {
+        match Instruction::parse(1, 0).expect("parse failed") {
             Instruction::Add(_, _, _) => {}
             _ => panic!("expected Add"),
         }
@@ -333,7 +926,7 @@ mod tests {
 
     #[test]
     fn instruction_parse_supports_multiply() {
-        match Instruction::parse(2) {
+        match Instruction::parse(2, 0).expect("parse failed") {
             Instruction::Multiply(_, _, _) => {}
             _ => panic!("expected Multiply"),
         }
@@ -341,7 +934,7 @@ mod tests {
 
     #[test]
     fn instruction_parse_supports_input() {
-        match Instruction::parse(3) {
+        match Instruction::parse(3, 0).expect("parse failed") {
             Instruction::Input(_) => {}
             _ => panic!("expected Input"),
         }
@@ -349,7 +942,7 @@ mod tests {
 
     #[test]
     fn instruction_parse_supports_output() {
-        match Instruction::parse(4) {
+        match Instruction::parse(4, 0).expect("parse failed") {
             Instruction::Output(_) => {}
             _ => panic!("expected Output"),
         }
@@ -357,18 +950,34 @@ mod tests {
 
     #[test]
     fn instruction_parse_supports_halt() {
-        assert_eq!(Instruction::Halt, Instruction::parse(99));
+        assert_eq!(Ok(Instruction::Halt), Instruction::parse(99, 0));
     }
 
     #[test]
-    #[should_panic]
-    fn instruction_parse_panics_on_unsupported_opcode() {
-        Instruction::parse(0);
+    fn instruction_parse_rejects_unsupported_opcode() {
+        assert_eq!(
+            Err(IntcompError::InvalidOpcode {
+                address: 7,
+                opcode: 0
+            }),
+            Instruction::parse(0, 7)
+        );
+    }
+
+    #[test]
+    fn instruction_parse_rejects_unsupported_mode() {
+        assert_eq!(
+            Err(IntcompError::InvalidMode {
+                address: 7,
+                digit: 3
+            }),
+            Instruction::parse(301, 7)
+        );
     }
 
     #[test]
     fn instruction_parse_supports_address() {
-        match Instruction::parse(1) {
+        match Instruction::parse(1, 0).expect("parse failed") {
             Instruction::Add(OperandMode::Position, _, _) => {}
             _ => panic!("expected Position"),
         }
@@ -376,18 +985,35 @@ mod tests {
 
     #[test]
     fn instruction_parse_supports_immediate() {
-        match Instruction::parse(101) {
+        match Instruction::parse(101, 0).expect("parse failed") {
             Instruction::Add(OperandMode::Immediate, _, _) => {}
             _ => panic!("expected Immediate"),
         }
     }
 
+    #[test]
+    fn instruction_decode_agrees_with_parse() {
+        for word in (-100..DECODED_WORDS + 100).chain(vec![100_001, 1_022_299]) {
+            assert_eq!(Instruction::parse(word, 3), Instruction::decode(word, 3));
+        }
+    }
+
     #[test]
     fn intcomp_execute_halts() {
         let initializer = vec![99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        assert_eq!(Ok(RunState::Halted), intcomp.execute());
+    }
+
+    #[test]
+    fn intcomp_execute_stays_halted() {
+        let initializer = vec![99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(Ok(RunState::Halted), intcomp.execute());
     }
 
     #[test]
@@ -395,7 +1021,7 @@ mod tests {
         let initializer = vec![1, 0, 0, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(2, intcomp.read_memory(0));
     }
@@ -405,7 +1031,7 @@ mod tests {
         let initializer = vec![2, 0, 0, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(4, intcomp.read_memory(0));
     }
@@ -416,7 +1042,7 @@ mod tests {
         let mut intcomp = Intcomp::new(&initializer);
 
         intcomp.send_input(1);
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(1, intcomp.read_memory(0));
     }
@@ -426,13 +1052,12 @@ mod tests {
         let initializer = vec![3, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
-
+        assert_eq!(Ok(RunState::AwaitingInput), intcomp.execute());
         assert_eq!(3, intcomp.read_memory(0));
 
         intcomp.send_input(1);
-        intcomp.execute();
 
+        assert_eq!(Ok(RunState::Halted), intcomp.execute());
         assert_eq!(1, intcomp.read_memory(0));
     }
 
@@ -441,7 +1066,7 @@ mod tests {
         let initializer = vec![104, 1, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(1, intcomp.receive_output().expect("no output available"));
     }
@@ -451,7 +1076,7 @@ mod tests {
         let initializer = vec![1105, 1, 4, 99, 1102, 0, 0, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(0, intcomp.read_memory(0));
     }
@@ -461,7 +1086,7 @@ mod tests {
         let initializer = vec![1106, 0, 4, 99, 1102, 0, 0, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(0, intcomp.read_memory(0));
     }
@@ -471,7 +1096,7 @@ mod tests {
         let initializer = vec![1107, 0, 1, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(1, intcomp.read_memory(0));
     }
@@ -481,7 +1106,7 @@ mod tests {
         let initializer = vec![1108, 0, 0, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(1, intcomp.read_memory(0));
     }
@@ -491,7 +1116,7 @@ mod tests {
         let initializer = vec![109, 4, 204, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(99, intcomp.receive_output().expect("no output available"));
     }
@@ -501,7 +1126,7 @@ mod tests {
         let initializer = vec![4, 10, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(0, intcomp.receive_output().expect("no output available"));
     }
@@ -511,18 +1136,251 @@ mod tests {
         let initializer = vec![204, 10, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(0, intcomp.receive_output().expect("no output available"));
     }
 
     #[test]
-    #[should_panic]
-    fn intcomp_execute_panics_on_unrecognized_opcode() {
-        let initializer = vec![0, 0, 0, 0, 99];
+    fn intcomp_execute_supports_paged_memory() {
+        let initializer = vec![1101, 1, 2, 1_000_000_000_000, 4, 1_000_000_000_000, 99];
+        let mut intcomp = Intcomp::<PagedMemory>::load(&initializer);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(3, intcomp.receive_output().expect("no output available"));
+        assert_eq!(3, intcomp.read_memory(1_000_000_000_000));
+    }
+
+    #[test]
+    fn intcomp_execute_fails_beyond_memory_limit() {
+        let initializer = vec![1101, 1, 2, 1_000_000_000_000, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        intcomp.set_memory_limit(Some(1024));
+
+        assert_eq!(
+            Err(IntcompError::AddressLimitExceeded {
+                address: 0,
+                target: 1_000_000_000_000
+            }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_fails_at_instruction_limit() {
+        // Loops forever.
+        let initializer = vec![1105, 1, 0];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        intcomp.set_instruction_limit(Some(10));
+
+        assert_eq!(Ok(RunState::StepLimitReached), intcomp.run_for(4));
+        assert_eq!(
+            Err(IntcompError::InstructionLimitExceeded { address: 0 }),
+            intcomp.execute()
+        );
+        assert_eq!(10, intcomp.instructions_executed());
+    }
+
+    #[test]
+    fn intcomp_execute_fails_at_output_limit() {
+        // Outputs 1 forever.
+        let initializer = vec![104, 1, 1105, 1, 0];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        intcomp.set_output_limit(Some(3));
+
+        assert_eq!(
+            Err(IntcompError::OutputLimitExceeded { address: 0 }),
+            intcomp.execute()
+        );
+
+        // Receiving output makes room for more.
+        assert_eq!(Some(1), intcomp.receive_output());
+        assert_eq!(
+            Err(IntcompError::OutputLimitExceeded { address: 0 }),
+            intcomp.execute()
+        );
+        assert_eq!(3, intcomp.output.len());
+    }
+
+    #[test]
+    fn intcomp_output_limit_counts_outputs_passed_on() {
+        let initializer = vec![104, 1, 1105, 1, 0];
+        let mut intcomp = Intcomp::new(&initializer);
+        let mut tape = Tape::new(&[]);
+
+        intcomp.set_output_limit(Some(3));
+
+        assert_eq!(
+            Err(IntcompError::OutputLimitExceeded { address: 0 }),
+            intcomp.execute_io(&mut tape)
+        );
+        assert_eq!(&[1, 1, 1], tape.outputs());
+
+        // Outputs already passed on can't be received, so they still count.
+        assert_eq!(
+            Err(IntcompError::OutputLimitExceeded { address: 0 }),
+            intcomp.execute_io(&mut tape)
+        );
+        assert_eq!(3, tape.outputs().len());
+    }
+
+    #[test]
+    fn intcomp_memory_limit_bounds_memory_held() {
+        // Writes to every thousandth address forever.
+        let initializer = vec![109, 1000, 21101, 1, 0, 0, 1105, 1, 0];
+        let error = Err(IntcompError::AddressLimitExceeded {
+            address: 2,
+            target: 5000,
+        });
+
+        let mut dense = Intcomp::new(&initializer);
+
+        dense.set_memory_limit(Some(4096));
+
+        assert_eq!(error, dense.execute());
+        assert_eq!(4001, dense.memory_len());
+
+        let mut paged = Intcomp::<PagedMemory>::load(&initializer);
+
+        paged.set_memory_limit(Some(4096));
+
+        assert_eq!(error, paged.execute());
+        assert_eq!(4, paged.memory.page_count());
+    }
+
+    #[test]
+    fn intcomp_execute_supports_self_modifying_code() {
+        // Outputs 7, turns its first instruction into a halt, and loops back.
+        let initializer = vec![104, 7, 1101, 0, 99, 0, 1105, 1, 0];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::Halted), intcomp.run_for(100));
+        assert_eq!(Some(7), intcomp.receive_output());
+        assert_eq!(None, intcomp.receive_output());
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_add_overflow() {
+        let initializer = vec![1101, i64::MAX, 1, 0, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::Overflow { address: 0 }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_multiply_overflow() {
+        let initializer = vec![1102, i64::MAX, 2, 0, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::Overflow { address: 0 }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_supports_wide_words() {
+        let initializer = vec![1102, i64::MAX, 4, 9, 1001, 9, 1, 9, 99, 0];
+        let mut intcomp = Intcomp::<DenseMemory<i128>>::load(&initializer);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(i64::MAX as i128 * 4 + 1, intcomp.read_memory(9));
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_unrecognized_opcode() {
+        let initializer = vec![1101, 0, 0, 4, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::InvalidOpcode {
+                address: 4,
+                opcode: 0
+            }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_immediate_write() {
+        let initializer = vec![11101, 0, 0, 0, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::ImmediateWrite { address: 0 }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_negative_position_operand() {
+        let initializer = vec![4, -1, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::NegativeAddress {
+                address: 0,
+                target: -1
+            }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_negative_relative_operand() {
+        let initializer = vec![109, 2, 204, -3, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::NegativeAddress {
+                address: 2,
+                target: -1
+            }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_allows_negative_relative_base() {
+        let initializer = vec![109, -1, 204, 1, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(109, intcomp.receive_output().expect("no output available"));
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_negative_jump() {
+        let initializer = vec![1105, 1, -4, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        assert_eq!(
+            Err(IntcompError::NegativeAddress {
+                address: 0,
+                target: -4
+            }),
+            intcomp.execute()
+        );
+    }
+
+    #[test]
+    fn intcomp_execute_fails_on_ip_out_of_bounds() {
+        let initializer = vec![1105, 1, 100, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(
+            Err(IntcompError::IpOutOfBounds { address: 100 }),
+            intcomp.execute()
+        );
     }
 
     #[test]
@@ -530,7 +1388,7 @@ mod tests {
         let initializer = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(3500, intcomp.read_memory(0));
     }
@@ -540,7 +1398,7 @@ mod tests {
         let initializer = vec![2, 3, 0, 3, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(6, intcomp.read_memory(3));
     }
@@ -550,7 +1408,7 @@ mod tests {
         let initializer = vec![2, 4, 4, 5, 99, 0];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(9801, intcomp.read_memory(5));
     }
@@ -560,7 +1418,7 @@ mod tests {
         let initializer = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(30, intcomp.read_memory(0));
     }
@@ -572,7 +1430,7 @@ mod tests {
         ];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         let mut output = Vec::new();
 
@@ -588,7 +1446,7 @@ mod tests {
         let initializer = vec![109, 1, 9, 2, 204, -6, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(204, intcomp.receive_output().expect("no output available"));
     }
@@ -598,7 +1456,7 @@ mod tests {
         let initializer = vec![109, 1, 109, 9, 204, -6, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(204, intcomp.receive_output().expect("no output available"));
     }
@@ -608,7 +1466,7 @@ mod tests {
         let initializer = vec![109, 1, 209, -1, 204, -106, 99];
         let mut intcomp = Intcomp::new(&initializer);
 
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(204, intcomp.receive_output().expect("no output available"));
     }
@@ -619,7 +1477,7 @@ mod tests {
         let mut intcomp = Intcomp::new(&initializer);
 
         intcomp.send_input(111);
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(111, intcomp.receive_output().expect("no output available"));
     }
@@ -630,17 +1488,134 @@ mod tests {
         let mut intcomp = Intcomp::new(&initializer);
 
         intcomp.send_input(222);
-        intcomp.execute();
+        intcomp.execute().expect("execution failed");
 
         assert_eq!(222, intcomp.receive_output().expect("no output available"));
     }
 
+    #[test]
+    fn intcomp_step_executes_one_instruction() {
+        let initializer = vec![1101, 1, 1, 0, 1101, 2, 2, 0, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::StepLimitReached), intcomp.step());
+        assert_eq!(2, intcomp.read_memory(0));
+        assert_eq!(4, intcomp.ip);
+
+        assert_eq!(Ok(RunState::StepLimitReached), intcomp.step());
+        assert_eq!(4, intcomp.read_memory(0));
+
+        assert_eq!(Ok(RunState::Halted), intcomp.step());
+    }
+
+    #[test]
+    fn intcomp_step_reports_output() {
+        let initializer = vec![104, 1, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::OutputProduced), intcomp.step());
+        assert_eq!(Some(1), intcomp.receive_output());
+    }
+
+    #[test]
+    fn intcomp_step_awaits_input() {
+        let initializer = vec![3, 0, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::AwaitingInput), intcomp.step());
+        assert_eq!(0, intcomp.ip);
+    }
+
+    #[test]
+    fn intcomp_run_until_output_yields_after_each_output() {
+        let initializer = vec![104, 1, 104, 2, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::OutputProduced), intcomp.run_until_output());
+        assert_eq!(Some(1), intcomp.receive_output());
+        assert_eq!(None, intcomp.receive_output());
+
+        assert_eq!(Ok(RunState::OutputProduced), intcomp.run_until_output());
+        assert_eq!(Some(2), intcomp.receive_output());
+
+        assert_eq!(Ok(RunState::Halted), intcomp.run_until_output());
+    }
+
+    #[test]
+    fn intcomp_run_for_stops_at_limit() {
+        // Loops forever, incrementing address 7.
+        let initializer = vec![1001, 7, 1, 7, 1105, 1, 0, 0];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::StepLimitReached), intcomp.run_for(10));
+        assert_eq!(5, intcomp.read_memory(7));
+    }
+
+    #[test]
+    fn intcomp_run_for_does_not_stop_on_output() {
+        let initializer = vec![104, 1, 104, 2, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+
+        assert_eq!(Ok(RunState::Halted), intcomp.run_for(10));
+        assert_eq!(Some(1), intcomp.receive_output());
+        assert_eq!(Some(2), intcomp.receive_output());
+    }
+
+    struct InterruptAt {
+        ip: Option<usize>,
+        write: Option<usize>,
+    }
+
+    impl Hook for InterruptAt {
+        fn before_instruction(&mut self, ip: usize, _bp: i64) -> bool {
+            self.ip != Some(ip)
+        }
+
+        fn before_write(&mut self, address: usize, _value: i64) -> bool {
+            self.write != Some(address)
+        }
+    }
+
+    #[test]
+    fn intcomp_execute_with_interrupts_before_instruction() {
+        let initializer = vec![1101, 1, 1, 0, 104, 5, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+        let mut hook = InterruptAt {
+            ip: Some(4),
+            write: None,
+        };
+
+        assert_eq!(Ok(RunState::Interrupted), intcomp.execute_with(&mut hook));
+        assert_eq!(4, intcomp.ip);
+        assert_eq!(None, intcomp.receive_output());
+    }
+
+    #[test]
+    fn intcomp_execute_with_interrupts_before_write() {
+        let initializer = vec![3, 5, 99];
+        let mut intcomp = Intcomp::new(&initializer);
+        let mut hook = InterruptAt {
+            ip: None,
+            write: Some(5),
+        };
+
+        intcomp.send_input(7);
+
+        assert_eq!(Ok(RunState::Interrupted), intcomp.execute_with(&mut hook));
+        assert_eq!(0, intcomp.ip);
+        assert_eq!(0, intcomp.read_memory(5));
+
+        // The input must still be available when the instruction is retried.
+        assert_eq!(Ok(RunState::Halted), intcomp.execute());
+        assert_eq!(7, intcomp.read_memory(5));
+    }
+
     #[test]
     fn intcomp_is_halted_detects_halt() {
         let initializer = vec![99];
         let intcomp = Intcomp::new(&initializer);
 
-        assert_eq!(true, intcomp.is_halted());
+        assert!(intcomp.is_halted());
     }
 
     #[test]
@@ -648,20 +1623,19 @@ mod tests {
         let initializer = vec![3, 0, 99];
         let intcomp = Intcomp::new(&initializer);
 
-        assert_eq!(false, intcomp.is_halted());
+        assert!(!intcomp.is_halted());
     }
 
     #[test]
     fn operand_mode_from_digit_works() {
-        assert_eq!(OperandMode::Position, OperandMode::from_digit(0));
-        assert_eq!(OperandMode::Immediate, OperandMode::from_digit(1));
-        assert_eq!(OperandMode::Relative, OperandMode::from_digit(2));
+        assert_eq!(Some(OperandMode::Position), OperandMode::from_digit(0));
+        assert_eq!(Some(OperandMode::Immediate), OperandMode::from_digit(1));
+        assert_eq!(Some(OperandMode::Relative), OperandMode::from_digit(2));
     }
 
     #[test]
-    #[should_panic]
-    fn operand_mode_from_digit_panics_on_unrecognized_digit() {
-        OperandMode::from_digit(9);
+    fn operand_mode_from_digit_rejects_unrecognized_digit() {
+        assert_eq!(None, OperandMode::from_digit(9));
     }
 
     #[test]
@@ -0,0 +1,455 @@
+use super::disassembler::disassemble_from;
+use super::{Instruction, OperandMode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Where control can go after a block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    /// Execution runs on into the block at this address.
+    Fallthrough(usize),
+
+    /// A jump with an immediate target.
+    Jump(usize),
+
+    /// A jump whose target is read from memory, so it can't be known
+    /// without running the program.
+    Unknown,
+}
+
+/// A run of instructions which always execute one after another.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    pub start: usize,
+
+    /// One past the last word of the last instruction.
+    pub end: usize,
+
+    /// The address of each instruction in the block.
+    pub instructions: Vec<usize>,
+
+    pub successors: Vec<Edge>,
+
+    /// The word at `end` doesn't decode as an instruction (or is past the end
+    /// of the program), so reaching it would fail.
+    pub invalid: bool,
+}
+
+/// A write by a reachable instruction into a word of reachable code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelfModification {
+    pub address: usize,
+    pub target: usize,
+}
+
+/// Instructions found by following control flow, before they're grouped into
+/// blocks.
+#[derive(Default)]
+struct Exploration {
+    instructions: BTreeMap<usize, (Instruction, Vec<Edge>)>,
+    invalid: BTreeSet<usize>,
+    predecessors: BTreeMap<usize, usize>,
+    jump_targets: BTreeSet<usize>,
+}
+
+/// The control-flow graph of a program, found without running it by following
+/// every path from address zero.  Jumps with targets in memory can't be
+/// followed, so anything only they lead to is counted as dead code.
+pub struct Analysis {
+    program: Vec<i64>,
+    blocks: Vec<Block>,
+    code: Vec<bool>,
+    self_modifications: Vec<SelfModification>,
+}
+
+impl Analysis {
+    pub fn new(program: &[i64]) -> Analysis {
+        let exploration = explore(program);
+        let mut code = vec![false; program.len()];
+
+        for (address, (instruction, _)) in &exploration.instructions {
+            let end = (address + instruction.width()).min(program.len());
+
+            for reachable in &mut code[*address..end] {
+                *reachable = true;
+            }
+        }
+
+        let self_modifications = exploration
+            .instructions
+            .iter()
+            .filter_map(|(address, (instruction, _))| {
+                // Relative targets depend on the relative base, so only
+                // position mode writes can be checked.
+                match target_mode(*instruction)? {
+                    OperandMode::Position => {}
+                    _ => return None,
+                }
+
+                let target = word(program, address + instruction.width() - 1);
+
+                match code.get(target as usize) {
+                    Some(true) if target >= 0 => Some(SelfModification {
+                        address: *address,
+                        target: target as usize,
+                    }),
+
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Analysis {
+            program: program.to_vec(),
+            blocks: group(&exploration),
+            code,
+            self_modifications,
+        }
+    }
+
+    /// Every reachable block, in address order.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn block_at(&self, start: usize) -> Option<&Block> {
+        self.blocks
+            .binary_search_by_key(&start, |block| block.start)
+            .ok()
+            .map(|index| &self.blocks[index])
+    }
+
+    /// Whether the word at `address` is part of a reachable instruction.
+    pub fn is_reachable(&self, address: usize) -> bool {
+        self.code.get(address).cloned().unwrap_or(false)
+    }
+
+    /// The runs of words which no reachable instruction covers.  These may be
+    /// data rather than code.
+    pub fn dead_code(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+
+        for (address, reachable) in self.code.iter().enumerate() {
+            if *reachable {
+                continue;
+            }
+
+            match ranges.last_mut() {
+                Some(range) if range.end == address => range.end += 1,
+                _ => ranges.push(address..address + 1),
+            }
+        }
+
+        ranges
+    }
+
+    /// Writes which would change the program's own reachable code.
+    pub fn self_modifications(&self) -> &[SelfModification] {
+        &self.self_modifications
+    }
+
+    /// The graph in Graphviz's DOT language, with each block labelled with
+    /// its listing.  Fallthrough edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph program {\n    node [shape=box, fontname=monospace];\n");
+        let mut unknown = false;
+
+        for block in &self.blocks {
+            let mut label = String::new();
+
+            for address in &block.instructions {
+                if let Some(line) = disassemble_from(&self.program, *address).next() {
+                    write!(label, "{}\\l", line).expect("writing to a string failed");
+                }
+            }
+
+            if block.invalid {
+                write!(label, "{:04}: invalid\\l", block.end).expect("writing to a string failed");
+            }
+
+            let colour = if block.invalid { ", color=red" } else { "" };
+
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, colour)
+                .expect("writing to a string failed");
+
+            for edge in &block.successors {
+                let line = match edge {
+                    Edge::Fallthrough(to) => format!("b{} -> b{} [style=dashed]", block.start, to),
+                    Edge::Jump(to) => format!("b{} -> b{}", block.start, to),
+
+                    Edge::Unknown => {
+                        unknown = true;
+                        format!("b{} -> unknown", block.start)
+                    }
+                };
+
+                writeln!(dot, "    {};", line).expect("writing to a string failed");
+            }
+        }
+
+        if unknown {
+            dot.push_str("    unknown [label=\"?\", shape=diamond];\n");
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+/// Memory beyond the end of the program reads as zero, as it does when
+/// running.
+fn word(program: &[i64], address: usize) -> i64 {
+    program.get(address).cloned().unwrap_or(0)
+}
+
+/// The mode of the operand an instruction writes, if it writes one.
+fn target_mode(instruction: Instruction) -> Option<OperandMode> {
+    match instruction {
+        Instruction::Add(_, _, target)
+        | Instruction::Multiply(_, _, target)
+        | Instruction::LessThan(_, _, target)
+        | Instruction::Equals(_, _, target)
+        | Instruction::Input(target) => Some(target),
+
+        _ => None,
+    }
+}
+
+/// Where control can go after the instruction at `address`.
+fn successors(program: &[i64], address: usize, instruction: Instruction) -> Vec<Edge> {
+    let next = address + instruction.width();
+
+    let (condition, jump_to, jump_if) = match instruction {
+        Instruction::Halt => return vec![],
+        Instruction::JumpIfTrue(condition, jump_to) => (condition, jump_to, true),
+        Instruction::JumpIfFalse(condition, jump_to) => (condition, jump_to, false),
+        _ => return vec![Edge::Fallthrough(next)],
+    };
+
+    let jump = match jump_to {
+        OperandMode::Immediate => match word(program, address + 2) {
+            target if target >= 0 => Edge::Jump(target as usize),
+            _ => Edge::Unknown,
+        },
+
+        _ => Edge::Unknown,
+    };
+
+    match condition {
+        OperandMode::Immediate if (word(program, address + 1) != 0) == jump_if => vec![jump],
+        OperandMode::Immediate => vec![Edge::Fallthrough(next)],
+        _ => vec![jump, Edge::Fallthrough(next)],
+    }
+}
+
+/// Follow every path from address zero, decoding instructions as they would
+/// be decoded when running.
+fn explore(program: &[i64]) -> Exploration {
+    let mut exploration = Exploration::default();
+    let mut pending = vec![0];
+
+    *exploration.predecessors.entry(0).or_insert(0) += 1;
+
+    while let Some(address) = pending.pop() {
+        if exploration.instructions.contains_key(&address) || exploration.invalid.contains(&address)
+        {
+            continue;
+        }
+
+        let instruction = match program
+            .get(address)
+            .map(|word| Instruction::parse(*word, address))
+        {
+            Some(Ok(instruction)) if target_mode(instruction) != Some(OperandMode::Immediate) => {
+                instruction
+            }
+
+            _ => {
+                exploration.invalid.insert(address);
+                continue;
+            }
+        };
+
+        let edges = successors(program, address, instruction);
+
+        for edge in &edges {
+            let to = match edge {
+                Edge::Fallthrough(to) => *to,
+
+                Edge::Jump(to) => {
+                    exploration.jump_targets.insert(*to);
+                    *to
+                }
+
+                Edge::Unknown => continue,
+            };
+
+            *exploration.predecessors.entry(to).or_insert(0) += 1;
+            pending.push(to);
+        }
+
+        // The instruction after a conditional jump starts a block of its own.
+        if edges.len() > 1 {
+            exploration
+                .jump_targets
+                .insert(address + instruction.width());
+        }
+
+        exploration
+            .instructions
+            .insert(address, (instruction, edges));
+    }
+
+    exploration
+}
+
+/// Split the explored instructions into blocks.  A block starts wherever
+/// control can arrive from more than one place, or by a jump.
+fn group(exploration: &Exploration) -> Vec<Block> {
+    let is_leader = |address: usize| {
+        address == 0
+            || exploration.jump_targets.contains(&address)
+            || exploration.predecessors.get(&address) != Some(&1)
+    };
+
+    let leaders = exploration
+        .instructions
+        .keys()
+        .chain(&exploration.invalid)
+        .cloned()
+        .filter(|address| is_leader(*address))
+        .collect::<BTreeSet<_>>();
+
+    leaders
+        .into_iter()
+        .map(|start| {
+            let mut block = Block {
+                start,
+                end: start,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+                invalid: false,
+            };
+
+            loop {
+                let (instruction, edges) = match exploration.instructions.get(&block.end) {
+                    Some(decoded) => decoded,
+
+                    None => {
+                        block.invalid = true;
+                        break;
+                    }
+                };
+
+                block.instructions.push(block.end);
+
+                match edges.as_slice() {
+                    [Edge::Fallthrough(next)] if !is_leader(*next) => block.end = *next,
+
+                    _ => {
+                        block.end += instruction.width();
+                        block.successors = edges.clone();
+                        break;
+                    }
+                }
+            }
+
+            block
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::fixtures::COUNTDOWN;
+
+    #[test]
+    fn analysis_finds_blocks() {
+        let analysis = Analysis::new(&COUNTDOWN);
+
+        assert_eq!(
+            vec![(0, 9), (9, 12), (14, 15)],
+            analysis
+                .blocks()
+                .iter()
+                .map(|block| (block.start, block.end))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Edge::Jump(0), Edge::Fallthrough(9)],
+            analysis.block_at(0).expect("no block at 0").successors
+        );
+        assert_eq!(
+            vec![Edge::Jump(14)],
+            analysis.block_at(9).expect("no block at 9").successors
+        );
+    }
+
+    #[test]
+    fn analysis_finds_dead_code() {
+        let analysis = Analysis::new(&COUNTDOWN);
+
+        assert_eq!(vec![12..14, 15..16], analysis.dead_code());
+        assert!(analysis.is_reachable(10));
+        assert!(!analysis.is_reachable(12));
+    }
+
+    #[test]
+    fn analysis_marks_indirect_jumps_unknown() {
+        // Jumps to the address stored at [4] while [5] is nonzero.
+        let analysis = Analysis::new(&[5, 5, 4, 99, 3, 1]);
+
+        assert_eq!(
+            vec![Edge::Unknown, Edge::Fallthrough(3)],
+            analysis.blocks()[0].successors
+        );
+        assert_eq!(vec![4..6], analysis.dead_code());
+    }
+
+    #[test]
+    fn analysis_tolerates_undecodable_data() {
+        let analysis = Analysis::new(&[104, 1, 99, 0, 12345, -7]);
+
+        assert_eq!(1, analysis.blocks().len());
+        assert!(!analysis.blocks()[0].invalid);
+        assert_eq!(vec![3..6], analysis.dead_code());
+    }
+
+    #[test]
+    fn analysis_reports_running_into_data() {
+        let analysis = Analysis::new(&[104, 1, 0]);
+        let block = &analysis.blocks()[0];
+
+        assert!(block.invalid);
+        assert_eq!(2, block.end);
+        assert!(block.successors.is_empty());
+    }
+
+    #[test]
+    fn analysis_finds_self_modifying_writes() {
+        // Overwrites the halt at 10 with an output and jumps to it.
+        let analysis = Analysis::new(&[1101, 0, 4, 10, 1105, 1, 10, 99, 0, 0, 99]);
+
+        assert_eq!(
+            vec![SelfModification {
+                address: 0,
+                target: 10
+            }],
+            analysis.self_modifications()
+        );
+    }
+
+    #[test]
+    fn analysis_exports_dot() {
+        let dot = Analysis::new(&[5, 5, 4, 99, 3, 1]).to_dot();
+
+        assert_eq!(
+            "digraph program {\n    node [shape=box, fontname=monospace];\n    b0 [label=\"0000: JT [5], [4]\\l\"];\n    b0 -> unknown;\n    b0 -> b3 [style=dashed];\n    b3 [label=\"0003: HLT\\l\"];\n    unknown [label=\"?\", shape=diamond];\n}\n",
+            dot
+        );
+    }
+}
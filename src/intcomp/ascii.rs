@@ -0,0 +1,175 @@
+use super::{Intcomp, IntcompError, RunState};
+use std::io::{self, BufRead, Write};
+
+/// Everything a program printed before it stopped, split into text and the
+/// values which were too large (or negative) to be ASCII.
+#[derive(Debug, PartialEq)]
+pub struct AsciiOutput {
+    pub text: String,
+    pub values: Vec<i64>,
+    pub state: RunState,
+}
+
+/// Drives an Intcode program which talks in lines of ASCII text.
+pub struct AsciiIntcomp {
+    intcomp: Intcomp,
+}
+
+impl AsciiIntcomp {
+    pub fn new(program: &[i64]) -> AsciiIntcomp {
+        AsciiIntcomp {
+            intcomp: Intcomp::new(program),
+        }
+    }
+
+    /// The underlying machine, e.g. for patching memory before starting.
+    pub fn intcomp(&mut self) -> &mut Intcomp {
+        &mut self.intcomp
+    }
+
+    /// Queue `line` as input, followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        for byte in line.bytes() {
+            self.intcomp.send_input(byte as i64);
+        }
+
+        self.intcomp.send_input(10);
+    }
+
+    /// Run until the program wants more input or halts, collecting everything
+    /// it printed along the way.
+    pub fn read_until_prompt(&mut self) -> Result<AsciiOutput, IntcompError> {
+        let state = self.intcomp.execute()?;
+        let mut text = String::new();
+        let mut values = Vec::new();
+
+        while let Some(value) = self.intcomp.receive_output() {
+            if (0..=127).contains(&value) {
+                text.push(value as u8 as char);
+            } else {
+                values.push(value);
+            }
+        }
+
+        Ok(AsciiOutput {
+            text,
+            values,
+            state,
+        })
+    }
+
+    /// Connect the program to a terminal: print what it says and send it each
+    /// line read from `input`, until it halts or `input` runs out.
+    pub fn interact<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        let mut lines = input.lines();
+
+        loop {
+            let printed = match self.read_until_prompt() {
+                Ok(printed) => printed,
+
+                Err(error) => {
+                    writeln!(output, "Error: {}.", error)?;
+
+                    return Ok(());
+                }
+            };
+
+            write!(output, "{}", printed.text)?;
+
+            for value in printed.values {
+                writeln!(output, "{}", value)?;
+            }
+
+            output.flush()?;
+
+            if printed.state == RunState::Halted {
+                return Ok(());
+            }
+
+            match lines.next() {
+                Some(line) => self.send_line(&line?),
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+impl From<Intcomp> for AsciiIntcomp {
+    fn from(intcomp: Intcomp) -> AsciiIntcomp {
+        AsciiIntcomp { intcomp }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::assembler::assemble;
+
+    /// Prints "?" and waits for a line, then prints the line's length (less
+    /// the newline) as a number and the line itself, and halts.
+    fn echo() -> AsciiIntcomp {
+        let program = assemble(
+            "
+                    OUT #63
+            loop:   IN [char]
+                    OUT [char]
+                    EQ [char], #10 -> [done]
+                    JT [done], #end
+                    ADD [count], #1 -> [count]
+                    JT #1, #loop
+            end:    ADD [count], #1000 -> [count]
+                    OUT [count]
+                    HLT
+            char:   .data 0
+            done:   .data 0
+            count:  .data 0
+            ",
+        )
+        .expect("assembly failed");
+
+        AsciiIntcomp::new(&program)
+    }
+
+    #[test]
+    fn ascii_intcomp_reads_prompt() {
+        let mut ascii = echo();
+
+        assert_eq!(
+            Ok(AsciiOutput {
+                text: "?".to_string(),
+                values: vec![],
+                state: RunState::AwaitingInput,
+            }),
+            ascii.read_until_prompt()
+        );
+    }
+
+    #[test]
+    fn ascii_intcomp_separates_text_from_values() {
+        let mut ascii = echo();
+
+        ascii.read_until_prompt().expect("execution failed");
+        ascii.send_line("hello");
+
+        assert_eq!(
+            Ok(AsciiOutput {
+                text: "hello\n".to_string(),
+                values: vec![1005],
+                state: RunState::Halted,
+            }),
+            ascii.read_until_prompt()
+        );
+    }
+
+    #[test]
+    fn ascii_intcomp_interacts() {
+        let mut ascii = echo();
+        let mut output = Vec::new();
+
+        ascii
+            .interact(&b"hi\nignored\n"[..], &mut output)
+            .expect("interaction failed");
+
+        assert_eq!("?hi\n1002\n", String::from_utf8_lossy(&output));
+    }
+}
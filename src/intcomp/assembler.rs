@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Mnemonic, opcode, number of operands read and whether a target is written.
+const MNEMONICS: [(&str, i64, usize, bool); 10] = [
+    ("add", 1, 2, true),
+    ("mul", 2, 2, true),
+    ("in", 3, 0, true),
+    ("out", 4, 1, false),
+    ("jt", 5, 2, false),
+    ("jf", 6, 2, false),
+    ("lt", 7, 2, true),
+    ("eq", 8, 2, true),
+    ("arb", 9, 1, false),
+    ("hlt", 99, 0, false),
+];
+
+/// Everything which can go wrong while assembling a program.  Each variant
+/// records the (1-based) line on which the problem was found.
+#[derive(Debug, PartialEq)]
+pub enum AssemblerError {
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    WrongOperandCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    ImmediateTarget {
+        line: usize,
+    },
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    AddressMismatch {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblerError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "line {}: unknown mnemonic '{}'", line, mnemonic)
+            }
+
+            AssemblerError::InvalidOperand { line, operand } => {
+                write!(f, "line {}: invalid operand '{}'", line, operand)
+            }
+
+            AssemblerError::WrongOperandCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} operands, found {}",
+                line, expected, found
+            ),
+
+            AssemblerError::ImmediateTarget { line } => {
+                write!(f, "line {}: target operand cannot be immediate", line)
+            }
+
+            AssemblerError::UndefinedLabel { line, label } => {
+                write!(f, "line {}: undefined label '{}'", line, label)
+            }
+
+            AssemblerError::DuplicateLabel { line, label } => {
+                write!(f, "line {}: label '{}' is already defined", line, label)
+            }
+
+            AssemblerError::AddressMismatch {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: address {} given, but the instruction is at {}",
+                line, expected, found
+            ),
+        }
+    }
+}
+
+impl Error for AssemblerError {}
+
+/// A number, or a label plus an offset which is resolved once every label's
+/// address is known.
+#[derive(Debug)]
+enum Value {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Position(Value),
+    Immediate(Value),
+    Relative(Value),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(i64, Vec<Operand>),
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn word_count(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    text.parse::<i64>().ok()
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+
+        _ => false,
+    }
+}
+
+/// Parse a number, a label, or a label followed by `+N` or `-N`.
+fn parse_value(text: &str) -> Option<Value> {
+    let text = text.trim();
+
+    if let Some(number) = parse_number(text) {
+        return Some(Value::Number(number));
+    }
+
+    match text.rfind(['+', '-']) {
+        Some(index) if index > 0 => {
+            let label = text[..index].trim();
+            let offset = parse_number(&text[index..].replace(' ', ""))?;
+
+            if is_label(label) {
+                Some(Value::Label(label.to_string(), offset))
+            } else {
+                None
+            }
+        }
+
+        _ if is_label(text) => Some(Value::Label(text.to_string(), 0)),
+        _ => None,
+    }
+}
+
+/// Parse `#value`, `[value]`, `[rb]`, `[rb+value]` or `[rb-value]`.
+fn parse_operand(text: &str) -> Option<Operand> {
+    if let Some(value) = text.strip_prefix('#') {
+        return parse_value(value).map(Operand::Immediate);
+    }
+
+    let inner = text.strip_prefix('[')?.strip_suffix(']')?.trim();
+
+    match inner.strip_prefix("rb") {
+        Some("") => Some(Operand::Relative(Value::Number(0))),
+
+        Some(offset) => {
+            let offset = offset.trim_start();
+
+            if let Some(value) = offset.strip_prefix('+') {
+                parse_value(value).map(Operand::Relative)
+            } else if let Some(value) = offset.strip_prefix('-') {
+                match parse_value(value)? {
+                    Value::Number(number) => Some(Operand::Relative(Value::Number(-number))),
+                    Value::Label(_, _) => None,
+                }
+            } else {
+                None
+            }
+        }
+
+        None => parse_value(inner).map(Operand::Position),
+    }
+}
+
+/// Split the operand list on commas, treating the `->` which the disassembler
+/// places before a target as just another separator.
+fn split_operands(text: &str) -> Vec<&str> {
+    text.split(',')
+        .flat_map(|part| part.split("->"))
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn parse_statement(line: usize, text: &str) -> Result<Statement, AssemblerError> {
+    let (mnemonic, rest) = match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], &text[index..]),
+        None => (text, ""),
+    };
+
+    let operands = split_operands(rest);
+    let lowercase = mnemonic.to_ascii_lowercase();
+
+    if lowercase == ".data" || lowercase == "data" {
+        return operands
+            .iter()
+            .map(|operand| {
+                parse_value(operand).ok_or_else(|| AssemblerError::InvalidOperand {
+                    line,
+                    operand: operand.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Statement::Data);
+    }
+
+    let (_, opcode, reads, writes) = MNEMONICS
+        .iter()
+        .find(|(name, _, _, _)| *name == lowercase)
+        .ok_or_else(|| AssemblerError::UnknownMnemonic {
+            line,
+            mnemonic: mnemonic.to_string(),
+        })?;
+
+    let expected = reads + if *writes { 1 } else { 0 };
+
+    if operands.len() != expected {
+        return Err(AssemblerError::WrongOperandCount {
+            line,
+            expected,
+            found: operands.len(),
+        });
+    }
+
+    let operands = operands
+        .iter()
+        .map(|operand| {
+            parse_operand(operand).ok_or_else(|| AssemblerError::InvalidOperand {
+                line,
+                operand: operand.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if *writes {
+        if let Some(Operand::Immediate(_)) = operands.last() {
+            return Err(AssemblerError::ImmediateTarget { line });
+        }
+    }
+
+    Ok(Statement::Instruction(*opcode, operands))
+}
+
+fn resolve(
+    line: usize,
+    value: &Value,
+    labels: &HashMap<String, usize>,
+) -> Result<i64, AssemblerError> {
+    match value {
+        Value::Number(number) => Ok(*number),
+
+        Value::Label(label, offset) => match labels.get(label) {
+            Some(address) => Ok(*address as i64 + offset),
+
+            None => Err(AssemblerError::UndefinedLabel {
+                line,
+                label: label.clone(),
+            }),
+        },
+    }
+}
+
+/// Assemble mnemonic source into a program which can be loaded by
+/// `Intcomp::new`.
+///
+/// Each line holds an optional label (`name:`), then an instruction or a
+/// `.data` directive, then an optional `;` comment.  Operands are written as
+/// `[address]` (position), `#value` (immediate) or `[rb+offset]` (relative),
+/// and any value may be a label.  Lines from a disassembly listing are also
+/// accepted, in which case the leading address is checked rather than being
+/// treated as a label.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssemblerError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != address {
+                    return Err(AssemblerError::AddressMismatch {
+                        line,
+                        expected,
+                        found: address,
+                    });
+                }
+            } else if is_label(label) {
+                if labels.insert(label.to_string(), address).is_some() {
+                    return Err(AssemblerError::DuplicateLabel {
+                        line,
+                        label: label.to_string(),
+                    });
+                }
+            } else {
+                break;
+            }
+
+            text = text[colon + 1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line, text)?;
+
+        address += statement.word_count();
+        statements.push((line, statement));
+    }
+
+    let mut program = Vec::with_capacity(address);
+
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let mut instruction = opcode;
+                let mut mode_base = 100;
+                let mut words = Vec::new();
+
+                for operand in &operands {
+                    let (mode, value) = match operand {
+                        Operand::Position(value) => (0, value),
+                        Operand::Immediate(value) => (1, value),
+                        Operand::Relative(value) => (2, value),
+                    };
+
+                    instruction += mode * mode_base;
+                    mode_base *= 10;
+                    words.push(resolve(line, value, &labels)?);
+                }
+
+                program.push(instruction);
+                program.extend(words);
+            }
+
+            Statement::Data(values) => {
+                for value in &values {
+                    program.push(resolve(line, value, &labels)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::disassembler::disassemble;
+    use super::super::Intcomp;
+    use super::*;
+
+    fn listing(program: &[i64]) -> String {
+        disassemble(program)
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn assemble_encodes_operand_modes() {
+        assert_eq!(Ok(vec![1201, 3, 5, 100]), assemble("add [rb+3], #5, [100]"));
+    }
+
+    #[test]
+    fn assemble_supports_every_mnemonic() {
+        let source = "
+            add [0], [0], [0]
+            mul [0], [0], [0]
+            in [0]
+            out [0]
+            jt [0], [0]
+            jf [0], [0]
+            lt [0], [0], [0]
+            eq [0], [0], [0]
+            arb [0]
+            hlt
+        ";
+
+        assert_eq!(
+            Ok(vec![
+                1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 4, 0, 5, 0, 0, 6, 0, 0, 7, 0, 0, 0, 8, 0, 0, 0, 9, 0,
+                99,
+            ]),
+            assemble(source)
+        );
+    }
+
+    #[test]
+    fn assemble_ignores_case_and_comments() {
+        assert_eq!(
+            Ok(vec![104, 1, 99]),
+            assemble("OUT #1 ; say hello\n; bye\nHlt")
+        );
+    }
+
+    #[test]
+    fn assemble_resolves_labels() {
+        let source = "
+            start: jt #1, #end
+                   .data 7
+            end:   out [value+1]
+                   hlt
+            value: .data 10, 20
+        ";
+
+        assert_eq!(Ok(vec![1105, 1, 4, 7, 4, 8, 99, 10, 20]), assemble(source));
+    }
+
+    #[test]
+    fn assemble_supports_negative_relative_offsets() {
+        assert_eq!(Ok(vec![204, -1, 204, 0]), assemble("out [rb-1]\nout [rb]"));
+    }
+
+    #[test]
+    fn assemble_output_runs() {
+        let source = "
+                  in [counter]
+            loop: out [counter]
+                  add [counter], #-1, [counter]
+                  jt [counter], #loop
+                  hlt
+            counter: .data 0
+        ";
+
+        let program = assemble(source).expect("assembly failed");
+        let mut intcomp = Intcomp::new(&program);
+
+        intcomp.send_input(3);
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(Some(3), intcomp.receive_output());
+        assert_eq!(Some(2), intcomp.receive_output());
+        assert_eq!(Some(1), intcomp.receive_output());
+        assert_eq!(None, intcomp.receive_output());
+    }
+
+    #[test]
+    fn assemble_rejects_unknown_mnemonic() {
+        assert_eq!(
+            Err(AssemblerError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "nop".to_string()
+            }),
+            assemble("hlt\nnop")
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_wrong_operand_count() {
+        assert_eq!(
+            Err(AssemblerError::WrongOperandCount {
+                line: 1,
+                expected: 3,
+                found: 2
+            }),
+            assemble("add #1, #2")
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_invalid_operand() {
+        assert_eq!(
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "5".to_string()
+            }),
+            assemble("out 5")
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_immediate_target() {
+        assert_eq!(
+            Err(AssemblerError::ImmediateTarget { line: 1 }),
+            assemble("add #1, #2, #3")
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_undefined_label() {
+        assert_eq!(
+            Err(AssemblerError::UndefinedLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            }),
+            assemble("jt #1, #nowhere")
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_duplicate_label() {
+        assert_eq!(
+            Err(AssemblerError::DuplicateLabel {
+                line: 2,
+                label: "here".to_string()
+            }),
+            assemble("here: hlt\nhere: hlt")
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_mismatched_listing_address() {
+        assert_eq!(
+            Err(AssemblerError::AddressMismatch {
+                line: 2,
+                expected: 3,
+                found: 1
+            }),
+            assemble("0000: HLT\n0003: HLT")
+        );
+    }
+
+    #[test]
+    fn assemble_then_disassemble_round_trips() {
+        let source = "
+                  arb #1
+                  out [rb-1]
+                  add [100], #1, [100]
+                  eq [100], #16, [101]
+                  jf [101], #0
+                  hlt
+        ";
+
+        let program = assemble(source).expect("assembly failed");
+
+        assert_eq!(Ok(program.clone()), assemble(&listing(&program)));
+    }
+
+    #[test]
+    fn disassemble_then_assemble_round_trips() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99, 0, 10099,
+            11101, 3,
+        ];
+
+        assert_eq!(Ok(program.clone()), assemble(&listing(&program)));
+    }
+}
@@ -0,0 +1,416 @@
+use super::io::IntcodeIo;
+use super::{Intcomp, IntcompError, NoHook, RunState};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// How many instructions a machine runs each time it's polled before letting
+/// other tasks on the executor have a turn.
+const SLICE: usize = 10_000;
+
+/// Everything shared between a machine and its handles.
+#[derive(Default)]
+struct State {
+    input: VecDeque<i64>,
+    output: VecDeque<i64>,
+
+    /// `Inputs` handles which haven't been dropped yet.
+    senders: usize,
+
+    /// The machine has stopped, or been dropped without stopping.
+    finished: bool,
+
+    /// The `Outputs` stream has been dropped, so nothing will read what's
+    /// already been output.
+    abandoned: bool,
+
+    machine: Option<Waker>,
+    reader: Option<Waker>,
+}
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<State>>);
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.0.lock().expect("async machine state poisoned")
+    }
+}
+
+fn wake(waker: &mut Option<Waker>) {
+    if let Some(waker) = waker.take() {
+        waker.wake();
+    }
+}
+
+impl IntcodeIo for Shared {
+    fn input(&mut self) -> Option<i64> {
+        self.lock().input.pop_front()
+    }
+
+    fn output(&mut self, value: i64) {
+        let mut state = self.lock();
+
+        state.output.push_back(value);
+        wake(&mut state.reader);
+    }
+
+    fn queued_outputs(&self) -> Option<usize> {
+        Some(self.lock().output.len())
+    }
+}
+
+/// How an `AsyncIntcomp` finished.
+pub struct Stopped {
+    pub intcomp: Intcomp,
+
+    /// `AwaitingInput` means the machine wanted input after every `Inputs`
+    /// handle had been dropped.
+    pub state: Result<RunState, IntcompError>,
+}
+
+/// A machine run as a future, which suspends instead of returning when it
+/// needs input nobody has sent yet.  Create one with `connect`.
+pub struct AsyncIntcomp {
+    intcomp: Option<Intcomp>,
+    shared: Shared,
+}
+
+/// Sends input to an `AsyncIntcomp`.  Handles can be cloned, and the machine
+/// stops waiting for input once they've all been dropped.
+pub struct Inputs {
+    shared: Shared,
+}
+
+/// The outputs of an `AsyncIntcomp`, as a stream which ends once the machine
+/// stops and everything it output has been read.
+pub struct Outputs {
+    shared: Shared,
+}
+
+/// Wrap `intcomp` for an async executor, returning the machine, a handle for
+/// sending it input and a stream of its output.  Nothing runs until the
+/// machine is polled, and inputs already queued with `Intcomp::send_input`
+/// are read first.
+///
+/// An output limit set with `Intcomp::set_output_limit` counts outputs which
+/// haven't been read from the stream yet.  While that many are waiting, the
+/// machine suspends until some are read.  It only fails with
+/// `OutputLimitExceeded` if the stream has been dropped.
+pub fn connect(intcomp: Intcomp) -> (AsyncIntcomp, Inputs, Outputs) {
+    let shared = Shared::default();
+
+    shared.lock().senders = 1;
+
+    (
+        AsyncIntcomp {
+            intcomp: Some(intcomp),
+            shared: shared.clone(),
+        },
+        Inputs {
+            shared: shared.clone(),
+        },
+        Outputs { shared },
+    )
+}
+
+impl AsyncIntcomp {
+    fn finish(&mut self, intcomp: Intcomp, state: Result<RunState, IntcompError>) -> Stopped {
+        let mut shared = self.shared.lock();
+
+        shared.finished = true;
+        wake(&mut shared.reader);
+
+        Stopped { intcomp, state }
+    }
+}
+
+impl Future for AsyncIntcomp {
+    type Output = Stopped;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Stopped> {
+        let this = self.get_mut();
+
+        let mut intcomp = this
+            .intcomp
+            .take()
+            .expect("AsyncIntcomp polled after it stopped");
+
+        let mut io = this.shared.clone();
+        let state = intcomp.run(Some(SLICE), false, &mut NoHook, &mut io);
+
+        match state {
+            Ok(RunState::StepLimitReached) => cx.waker().wake_by_ref(),
+
+            Ok(RunState::AwaitingInput) => {
+                let mut shared = this.shared.lock();
+
+                // Input may have arrived since the machine last looked.
+                if !shared.input.is_empty() {
+                    cx.waker().wake_by_ref();
+                } else if shared.senders > 0 {
+                    shared.machine = Some(cx.waker().clone());
+                } else {
+                    drop(shared);
+
+                    return Poll::Ready(this.finish(intcomp, state));
+                }
+            }
+
+            Err(IntcompError::OutputLimitExceeded { .. }) => {
+                let mut shared = this.shared.lock();
+                let limit = intcomp.output_limit.unwrap_or(0);
+
+                // Outputs may have been read since the machine last looked.
+                if shared.output.len() < limit {
+                    cx.waker().wake_by_ref();
+                } else if !shared.abandoned {
+                    shared.machine = Some(cx.waker().clone());
+                } else {
+                    drop(shared);
+
+                    return Poll::Ready(this.finish(intcomp, state));
+                }
+            }
+
+            _ => return Poll::Ready(this.finish(intcomp, state)),
+        }
+
+        this.intcomp = Some(intcomp);
+
+        Poll::Pending
+    }
+}
+
+impl Drop for AsyncIntcomp {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+
+        shared.finished = true;
+        wake(&mut shared.reader);
+    }
+}
+
+impl Inputs {
+    pub fn send(&self, value: i64) {
+        let mut shared = self.shared.lock();
+
+        shared.input.push_back(value);
+        wake(&mut shared.machine);
+    }
+}
+
+impl Clone for Inputs {
+    fn clone(&self) -> Inputs {
+        self.shared.lock().senders += 1;
+
+        Inputs {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Inputs {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+
+        shared.senders -= 1;
+
+        if shared.senders == 0 {
+            wake(&mut shared.machine);
+        }
+    }
+}
+
+impl Stream for Outputs {
+    type Item = i64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        let mut shared = self.shared.lock();
+
+        if let Some(value) = shared.output.pop_front() {
+            // The machine may be waiting for room to output.
+            wake(&mut shared.machine);
+
+            Poll::Ready(Some(value))
+        } else if shared.finished {
+            Poll::Ready(None)
+        } else {
+            shared.reader = Some(cx.waker().clone());
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Outputs {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock();
+
+        shared.abandoned = true;
+        wake(&mut shared.machine);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::fixtures::DOUBLER;
+    use futures::executor::{block_on, LocalPool};
+    use futures::task::LocalSpawnExt;
+    use futures::StreamExt;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    #[test]
+    fn async_intcomp_streams_outputs() {
+        let (machine, inputs, outputs) = connect(Intcomp::new(&DOUBLER));
+
+        for value in &[1, 2, 3, 0] {
+            inputs.send(*value);
+        }
+
+        let stopped = block_on(machine);
+
+        assert_eq!(Ok(RunState::Halted), stopped.state);
+        assert_eq!(vec![2, 4, 6], block_on(outputs.collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn async_intcomp_suspends_awaiting_input() {
+        let (machine, inputs, mut outputs) = connect(Intcomp::new(&DOUBLER));
+        let mut pool = LocalPool::new();
+        let finished = Rc::new(RefCell::new(None));
+        let result = finished.clone();
+
+        pool.spawner()
+            .spawn_local(async move { *result.borrow_mut() = Some(machine.await.state) })
+            .expect("spawn failed");
+
+        pool.run_until_stalled();
+        assert_eq!(None, *finished.borrow());
+
+        inputs.send(21);
+        assert_eq!(Some(42), pool.run_until(outputs.next()));
+        assert_eq!(None, *finished.borrow());
+
+        inputs.send(0);
+        pool.run_until_stalled();
+        assert_eq!(Some(Ok(RunState::Halted)), *finished.borrow());
+        assert_eq!(None, pool.run_until(outputs.next()));
+    }
+
+    #[test]
+    fn async_intcomp_stops_once_inputs_are_dropped() {
+        let (machine, inputs, outputs) = connect(Intcomp::new(&DOUBLER));
+
+        inputs.clone().send(5);
+        drop(inputs);
+
+        let stopped = block_on(machine);
+
+        assert_eq!(Ok(RunState::AwaitingInput), stopped.state);
+        assert_eq!(vec![10], block_on(outputs.collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn async_intcomp_waits_for_outputs_to_be_read() {
+        let mut intcomp = Intcomp::new(&DOUBLER);
+
+        intcomp.set_output_limit(Some(1));
+
+        let (machine, inputs, mut outputs) = connect(intcomp);
+        let mut pool = LocalPool::new();
+        let finished = Rc::new(RefCell::new(None));
+        let result = finished.clone();
+
+        for value in &[1, 2, 3, 0] {
+            inputs.send(*value);
+        }
+
+        pool.spawner()
+            .spawn_local(async move { *result.borrow_mut() = Some(machine.await.state) })
+            .expect("spawn failed");
+
+        pool.run_until_stalled();
+        assert_eq!(None, *finished.borrow());
+
+        for expected in &[2, 4, 6] {
+            assert_eq!(Some(*expected), pool.run_until(outputs.next()));
+        }
+
+        pool.run_until_stalled();
+        assert_eq!(Some(Ok(RunState::Halted)), *finished.borrow());
+    }
+
+    #[test]
+    fn async_intcomp_fails_at_output_limit_once_outputs_are_dropped() {
+        let mut intcomp = Intcomp::new(&DOUBLER);
+
+        intcomp.set_output_limit(Some(1));
+
+        let (machine, inputs, outputs) = connect(intcomp);
+
+        inputs.send(1);
+        inputs.send(2);
+        drop(outputs);
+
+        assert_eq!(
+            Err(IntcompError::OutputLimitExceeded { address: 9 }),
+            block_on(machine).state
+        );
+    }
+
+    #[test]
+    fn async_intcomps_run_in_a_feedback_loop() {
+        // The second feedback loop example from Day 7, on a single thread.
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+
+        let mut pool = LocalPool::new();
+        let spawner = pool.spawner();
+        let mut ends = Vec::new();
+
+        for phase in &[9, 8, 7, 6, 5] {
+            let (machine, inputs, outputs) = connect(Intcomp::new(&program));
+
+            inputs.send(*phase);
+            spawner
+                .spawn_local(async move {
+                    machine.await;
+                })
+                .expect("spawn failed");
+            ends.push((inputs, outputs));
+        }
+
+        ends[0].0.send(0);
+
+        let thrusters = Rc::new(Cell::new(0));
+        let (inputs, outputs): (Vec<_>, Vec<_>) = ends.into_iter().unzip();
+        let mut inputs = inputs.into_iter().collect::<Vec<_>>();
+
+        inputs.rotate_left(1);
+
+        for (mut outputs, inputs) in outputs.into_iter().zip(inputs) {
+            let thrusters = thrusters.clone();
+
+            spawner
+                .spawn_local(async move {
+                    while let Some(value) = outputs.next().await {
+                        thrusters.set(value);
+                        inputs.send(value);
+                    }
+                })
+                .expect("spawn failed");
+        }
+
+        pool.run();
+
+        assert_eq!(139_629_729, thrusters.get());
+    }
+}
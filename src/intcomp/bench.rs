@@ -0,0 +1,23 @@
+//! Entry points for the benchmarks, which can't reach private items.  Not
+//! part of the public API.
+
+use super::Instruction;
+
+/// How many of `words` are valid instructions, parsing each digit by digit.
+pub fn parse(words: &[i64]) -> usize {
+    words
+        .iter()
+        .enumerate()
+        .filter(|(address, word)| Instruction::parse(**word, *address).is_ok())
+        .count()
+}
+
+/// As `parse`, but using the table of decoded instructions as the
+/// interpreter does.
+pub fn decode(words: &[i64]) -> usize {
+    words
+        .iter()
+        .enumerate()
+        .filter(|(address, word)| Instruction::decode(**word, *address).is_ok())
+        .count()
+}
@@ -0,0 +1,686 @@
+use super::analysis::Analysis;
+use super::memory::{DenseMemory, Memory};
+use super::{Instruction, Intcomp, IntcompError, OperandMode, RunState};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::mem;
+
+/// The state compiled code runs on: a machine's registers, memory and queues,
+/// taken out of it for the duration of the run.
+///
+/// Every method which returns an `Option` gives `None` where the interpreter
+/// would fail, or where the compiled code can't be trusted any more, and
+/// compiled code returns `None` in turn so that the interpreter can take over
+/// from the current instruction.  Until then, nothing has changed.
+pub struct Frame<'a> {
+    memory: Vec<i64>,
+    ip: usize,
+    bp: i64,
+    executed: u64,
+    memory_limit: Option<usize>,
+    input: VecDeque<i64>,
+    output: VecDeque<i64>,
+
+    /// Whether each address is the start of a compiled instruction.
+    code: &'a [bool],
+}
+
+impl Frame<'_> {
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    fn check(&self, address: i64) -> Option<usize> {
+        match self.memory_limit {
+            _ if address < 0 => None,
+            Some(limit) if address as usize >= limit => None,
+            _ => Some(address as usize),
+        }
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).cloned().unwrap_or(0)
+    }
+
+    /// The address the operand at `operand` refers to in mode `mode`.
+    fn address(&self, mode: u8, operand: usize) -> Option<usize> {
+        match mode {
+            0 => self.check(self.read(operand)),
+            1 => Some(operand),
+            2 => self.check(self.bp.checked_add(self.read(operand))?),
+            _ => None,
+        }
+    }
+
+    /// The value of the operand at `operand` in mode `mode`.
+    #[inline]
+    pub fn load(&self, mode: u8, operand: usize) -> Option<i64> {
+        Some(self.read(self.address(mode, operand)?))
+    }
+
+    /// The address the operand at `operand` writes to in mode `mode`.  Writing
+    /// over a compiled instruction gives `None`, leaving self-modifying code
+    /// to the interpreter.
+    #[inline]
+    pub fn target(&self, mode: u8, operand: usize) -> Option<usize> {
+        if mode == 1 {
+            return None;
+        }
+
+        let address = self.address(mode, operand)?;
+
+        match self.code.get(address) {
+            Some(true) => None,
+            _ => Some(address),
+        }
+    }
+
+    #[inline]
+    pub fn store(&mut self, address: usize, value: i64) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+
+        self.memory[address] = value;
+    }
+
+    pub fn input(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    pub fn output(&mut self, value: i64) {
+        self.output.push_back(value);
+    }
+
+    /// Finish the current instruction, moving on to the one at `next`.
+    #[inline]
+    pub fn advance(&mut self, next: usize) {
+        self.ip = next;
+        self.executed += 1;
+    }
+
+    /// Finish the current instruction by jumping to `target`.
+    #[inline]
+    pub fn jump(&mut self, target: i64) -> Option<()> {
+        let target = self.check(target)?;
+
+        self.advance(target);
+
+        Some(())
+    }
+
+    pub fn adjust_base(&mut self, offset: i64) -> Option<()> {
+        self.bp = self.bp.checked_add(offset)?;
+
+        Some(())
+    }
+
+    /// Finish the current instruction, which is a `Halt`.
+    pub fn halt(&mut self) -> RunState {
+        self.executed += 1;
+
+        RunState::Halted
+    }
+}
+
+/// Run `intcomp` with compiled code as `Intcomp::execute` would, falling back
+/// to the interpreter for anything the code doesn't cover.  `starts` are the
+/// addresses of the instructions compiled from `program`.
+///
+/// `body` should return the state the machine stopped in, or `None` to hand
+/// over to the interpreter.  It isn't used at all if the machine has a dialect,
+/// an instruction or output limit, or an instruction which differs from the
+/// compiled one.
+pub fn run<F>(
+    intcomp: &mut Intcomp,
+    program: &[i64],
+    starts: &[usize],
+    body: F,
+) -> Result<RunState, IntcompError>
+where
+    F: FnOnce(&mut Frame) -> Option<RunState>,
+{
+    let mut code = vec![false; program.len()];
+
+    for start in starts {
+        code[*start] = true;
+    }
+
+    enter(intcomp, program, starts, &code, body)
+}
+
+fn enter<F>(
+    intcomp: &mut Intcomp,
+    program: &[i64],
+    starts: &[usize],
+    code: &[bool],
+    body: F,
+) -> Result<RunState, IntcompError>
+where
+    F: FnOnce(&mut Frame) -> Option<RunState>,
+{
+    let compatible = intcomp.dialect.is_none()
+        && intcomp.instruction_limit.is_none()
+        && intcomp.output_limit.is_none()
+        && starts
+            .iter()
+            .all(|start| intcomp.memory.read(*start) == program[*start]);
+
+    if !compatible {
+        return intcomp.execute();
+    }
+
+    let len = intcomp.memory.len();
+    let mut frame = Frame {
+        memory: mem::take(&mut intcomp.memory).into_vec(),
+        ip: intcomp.ip,
+        bp: intcomp.bp,
+        executed: intcomp.executed,
+        memory_limit: intcomp.memory_limit,
+        input: mem::take(&mut intcomp.input),
+        output: mem::take(&mut intcomp.output),
+        code,
+    };
+
+    let queued = frame.output.len();
+    let state = body(&mut frame);
+
+    intcomp.memory = DenseMemory::from(frame.memory);
+    intcomp.memory.grow_to(len);
+    intcomp.ip = frame.ip;
+    intcomp.bp = frame.bp;
+    intcomp.executed = frame.executed;
+    intcomp.produced += frame.output.len() - queued;
+    intcomp.input = frame.input;
+    intcomp.output = frame.output;
+
+    match state {
+        Some(state) => Ok(state),
+        None => intcomp.execute(),
+    }
+}
+
+/// The digit which selects `mode`.
+fn digit(mode: OperandMode) -> u8 {
+    match mode {
+        OperandMode::Position => 0,
+        OperandMode::Immediate => 1,
+        OperandMode::Relative => 2,
+    }
+}
+
+/// What a compiled instruction did, if it didn't hand over to the interpreter.
+enum Flow {
+    Continue,
+    Stop(RunState),
+}
+
+type Op = Box<dyn Fn(&mut Frame) -> Option<Flow> + Send + Sync>;
+
+/// An arithmetic or comparison instruction at `address`, which writes
+/// `operation` of its operands.
+fn binary<F>(address: usize, modes: [OperandMode; 3], operation: F) -> Op
+where
+    F: Fn(i64, i64) -> Option<i64> + Send + Sync + 'static,
+{
+    let [left, right, target] = modes.map(digit);
+
+    Box::new(move |frame| {
+        let value = operation(
+            frame.load(left, address + 1)?,
+            frame.load(right, address + 2)?,
+        )?;
+        let target = frame.target(target, address + 3)?;
+
+        frame.store(target, value);
+        frame.advance(address + 4);
+
+        Some(Flow::Continue)
+    })
+}
+
+fn jump(address: usize, condition: OperandMode, target: OperandMode, when: bool) -> Op {
+    let (condition, target) = (digit(condition), digit(target));
+
+    Box::new(move |frame| {
+        let value = frame.load(condition, address + 1)?;
+        let target = frame.load(target, address + 2)?;
+
+        if (value != 0) == when {
+            frame.jump(target)?;
+        } else {
+            frame.advance(address + 3);
+        }
+
+        Some(Flow::Continue)
+    })
+}
+
+fn compile(address: usize, instruction: Instruction) -> Op {
+    match instruction {
+        Instruction::Add(left, right, target) => {
+            binary(address, [left, right, target], i64::checked_add)
+        }
+
+        Instruction::Multiply(left, right, target) => {
+            binary(address, [left, right, target], i64::checked_mul)
+        }
+
+        Instruction::LessThan(left, right, target) => {
+            binary(address, [left, right, target], |left, right| {
+                Some((left < right) as i64)
+            })
+        }
+
+        Instruction::Equals(left, right, target) => {
+            binary(address, [left, right, target], |left, right| {
+                Some((left == right) as i64)
+            })
+        }
+
+        Instruction::Input(target) => {
+            let target = digit(target);
+
+            Box::new(move |frame| {
+                let target = frame.target(target, address + 1)?;
+
+                match frame.input() {
+                    Some(value) => frame.store(target, value),
+                    None => return Some(Flow::Stop(RunState::AwaitingInput)),
+                }
+
+                frame.advance(address + 2);
+
+                Some(Flow::Continue)
+            })
+        }
+
+        Instruction::Output(mode) => {
+            let mode = digit(mode);
+
+            Box::new(move |frame| {
+                let value = frame.load(mode, address + 1)?;
+
+                frame.output(value);
+                frame.advance(address + 2);
+
+                Some(Flow::Continue)
+            })
+        }
+
+        Instruction::JumpIfTrue(condition, target) => jump(address, condition, target, true),
+        Instruction::JumpIfFalse(condition, target) => jump(address, condition, target, false),
+
+        Instruction::AdjustRelativeBase(mode) => {
+            let mode = digit(mode);
+
+            Box::new(move |frame| {
+                let offset = frame.load(mode, address + 1)?;
+
+                frame.adjust_base(offset)?;
+                frame.advance(address + 2);
+
+                Some(Flow::Continue)
+            })
+        }
+
+        Instruction::Halt => Box::new(|frame| Some(Flow::Stop(frame.halt()))),
+    }
+}
+
+/// Write the Rust source for the instruction at `address`, as an arm of the
+/// `match` in a generated module's `body`.
+fn emit(source: &mut String, address: usize, instruction: Instruction) -> std::fmt::Result {
+    let a = address;
+
+    writeln!(source, "            {} => {{", a)?;
+
+    let binary = |source: &mut String, modes: [OperandMode; 3], expression: &str| {
+        let [left, right, target] = modes.map(digit);
+        let left = format!("frame.load({}, {})?", left, a + 1);
+        let right = format!("frame.load({}, {})?", right, a + 2);
+
+        writeln!(
+            source,
+            "                let value = {};",
+            expression.replace("LEFT", &left).replace("RIGHT", &right)
+        )?;
+        writeln!(
+            source,
+            "                let target = frame.target({}, {})?;",
+            target,
+            a + 3
+        )?;
+        writeln!(source, "                frame.store(target, value);")?;
+        writeln!(source, "                frame.advance({});", a + 4)
+    };
+
+    let jump = |source: &mut String, condition, target, comparison| {
+        writeln!(
+            source,
+            "                let value = frame.load({}, {})?;",
+            digit(condition),
+            a + 1
+        )?;
+        writeln!(
+            source,
+            "                let target = frame.load({}, {})?;",
+            digit(target),
+            a + 2
+        )?;
+        writeln!(source, "                if value {} 0 {{", comparison)?;
+        writeln!(source, "                    frame.jump(target)?;")?;
+        writeln!(source, "                }} else {{")?;
+        writeln!(source, "                    frame.advance({});", a + 3)?;
+        writeln!(source, "                }}")
+    };
+
+    match instruction {
+        Instruction::Add(left, right, target) => {
+            binary(source, [left, right, target], "LEFT.checked_add(RIGHT)?")?
+        }
+
+        Instruction::Multiply(left, right, target) => {
+            binary(source, [left, right, target], "LEFT.checked_mul(RIGHT)?")?
+        }
+
+        Instruction::LessThan(left, right, target) => {
+            binary(source, [left, right, target], "(LEFT < RIGHT) as i64")?
+        }
+
+        Instruction::Equals(left, right, target) => {
+            binary(source, [left, right, target], "(LEFT == RIGHT) as i64")?
+        }
+
+        Instruction::Input(target) => {
+            writeln!(
+                source,
+                "                let target = frame.target({}, {})?;",
+                digit(target),
+                a + 1
+            )?;
+            writeln!(source, "                match frame.input() {{")?;
+            writeln!(
+                source,
+                "                    Some(value) => frame.store(target, value),"
+            )?;
+            writeln!(
+                source,
+                "                    None => return Some(RunState::AwaitingInput),"
+            )?;
+            writeln!(source, "                }}")?;
+            writeln!(source, "                frame.advance({});", a + 2)?;
+        }
+
+        Instruction::Output(mode) => {
+            writeln!(
+                source,
+                "                let value = frame.load({}, {})?;",
+                digit(mode),
+                a + 1
+            )?;
+            writeln!(source, "                frame.output(value);")?;
+            writeln!(source, "                frame.advance({});", a + 2)?;
+        }
+
+        Instruction::JumpIfTrue(condition, target) => jump(source, condition, target, "!=")?,
+        Instruction::JumpIfFalse(condition, target) => jump(source, condition, target, "==")?,
+
+        Instruction::AdjustRelativeBase(mode) => {
+            writeln!(
+                source,
+                "                let offset = frame.load({}, {})?;",
+                digit(mode),
+                a + 1
+            )?;
+            writeln!(source, "                frame.adjust_base(offset)?;")?;
+            writeln!(source, "                frame.advance({});", a + 2)?;
+        }
+
+        Instruction::Halt => writeln!(source, "                return Some(frame.halt());")?,
+    }
+
+    writeln!(source, "            }}")
+}
+
+/// Write `words` as the elements of an array, ten to a line.
+fn emit_array<T: ToString>(source: &mut String, words: &[T]) -> std::fmt::Result {
+    for line in words.chunks(10) {
+        let line = line.iter().map(T::to_string).collect::<Vec<_>>();
+
+        writeln!(source, "    {},", line.join(", "))?;
+    }
+
+    Ok(())
+}
+
+/// A program compiled ahead of time, for running many times over.
+///
+/// Every instruction reachable from address zero is compiled, reading its
+/// operands from memory as it runs, so patching them between runs is fine.
+/// Anything else hands over to the interpreter for the rest of the run: jumps
+/// to code which wasn't compiled, writes over a compiled instruction, and
+/// anything which would make the interpreter fail, so that errors come from
+/// the interpreter too.
+pub struct Compiled {
+    program: Vec<i64>,
+    starts: Vec<usize>,
+    code: Vec<bool>,
+    ops: Vec<Option<Op>>,
+}
+
+impl Compiled {
+    pub fn new(program: &[i64]) -> Compiled {
+        let mut starts = Vec::new();
+        let mut code = vec![false; program.len()];
+        let mut ops = (0..program.len()).map(|_| None).collect::<Vec<_>>();
+
+        for block in Analysis::new(program).blocks() {
+            for address in &block.instructions {
+                let address = *address;
+
+                if let Ok(instruction) = Instruction::decode(program[address], address) {
+                    starts.push(address);
+                    code[address] = true;
+                    ops[address] = Some(compile(address, instruction));
+                }
+            }
+        }
+
+        starts.sort_unstable();
+
+        Compiled {
+            program: program.to_vec(),
+            starts,
+            code,
+            ops,
+        }
+    }
+
+    /// The addresses of the compiled instructions.
+    pub fn instructions(&self) -> &[usize] {
+        &self.starts
+    }
+
+    /// Run as `Intcomp::execute` does.  `intcomp` should have been loaded with
+    /// the compiled program, but needn't be at the start of it.
+    pub fn execute(&self, intcomp: &mut Intcomp) -> Result<RunState, IntcompError> {
+        enter(intcomp, &self.program, &self.starts, &self.code, |frame| {
+            loop {
+                let op = self.ops.get(frame.ip)?.as_ref()?;
+
+                if let Flow::Stop(state) = op(frame)? {
+                    return Some(state);
+                }
+            }
+        })
+    }
+
+    /// Rust source for a module which runs the program as `execute` does, for
+    /// building into a crate which depends on this one.  The module has the
+    /// program as `PROGRAM` and an `execute` function which takes an
+    /// `Intcomp`.
+    pub fn to_rust(&self) -> String {
+        let mut source = String::new();
+
+        self.write_rust(&mut source)
+            .expect("writing to a String can't fail");
+
+        source
+    }
+
+    fn write_rust(&self, source: &mut String) -> std::fmt::Result {
+        writeln!(
+            source,
+            "// Compiled from an Intcode program by `aoc2019 compile`.\n\
+             \n\
+             use aoc2019::intcomp::compiler::{{self, Frame}};\n\
+             use aoc2019::intcomp::{{Intcomp, IntcompError, RunState}};\n"
+        )?;
+
+        writeln!(
+            source,
+            "pub const PROGRAM: [i64; {}] = [",
+            self.program.len()
+        )?;
+        emit_array(source, &self.program)?;
+        writeln!(source, "];\n")?;
+
+        writeln!(source, "const STARTS: [usize; {}] = [", self.starts.len())?;
+        emit_array(source, &self.starts)?;
+        writeln!(source, "];\n")?;
+
+        writeln!(
+            source,
+            "pub fn execute(intcomp: &mut Intcomp) -> Result<RunState, IntcompError> {{\n    \
+                 compiler::run(intcomp, &PROGRAM, &STARTS, body)\n\
+             }}\n"
+        )?;
+
+        writeln!(source, "fn body(frame: &mut Frame) -> Option<RunState> {{")?;
+        writeln!(source, "    loop {{")?;
+        writeln!(source, "        match frame.ip() {{")?;
+
+        for address in &self.starts {
+            let instruction = Instruction::decode(self.program[*address], *address)
+                .expect("compiled instructions decode");
+
+            emit(source, *address, instruction)?;
+        }
+
+        writeln!(source, "            _ => return None,")?;
+        writeln!(source, "        }}")?;
+        writeln!(source, "    }}")?;
+        writeln!(source, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::read_program;
+
+    /// Generated from `INPUT_COUNTDOWN` by `Compiled::to_rust`.
+    mod generated {
+        use crate as aoc2019;
+
+        include!("compiler/countdown.rs");
+    }
+
+    /// Reads a count, then outputs it and every number below it down to one.
+    /// Unlike `fixtures::COUNTDOWN`, which always counts from 3, the count is
+    /// an input, so the compiled code's input handling gets exercised too.
+    const INPUT_COUNTDOWN: [i64; 14] = [3, 13, 4, 13, 1001, 13, -1, 13, 1005, 13, 2, 99, 0, 0];
+
+    /// Run `program` with `inputs` both interpreted and compiled, checking
+    /// that the results, outputs and final states agree.
+    fn compare(program: &[i64], inputs: &[i64]) -> Vec<i64> {
+        let compiled = Compiled::new(program);
+        let mut interpreted = Intcomp::new(program);
+        let mut intcomp = Intcomp::new(program);
+
+        for input in inputs {
+            interpreted.send_input(*input);
+            intcomp.send_input(*input);
+        }
+
+        assert_eq!(interpreted.execute(), compiled.execute(&mut intcomp));
+        assert_eq!(interpreted.snapshot(), intcomp.snapshot());
+        assert_eq!(
+            interpreted.instructions_executed(),
+            intcomp.instructions_executed()
+        );
+
+        intcomp.snapshot().output
+    }
+
+    #[test]
+    fn compiled_matches_interpreter() {
+        assert_eq!(vec![3, 2, 1], compare(&INPUT_COUNTDOWN, &[3]));
+        assert_eq!(Vec::<i64>::new(), compare(&INPUT_COUNTDOWN, &[]));
+    }
+
+    #[test]
+    fn compiled_matches_interpreter_on_puzzle_inputs() {
+        compare(&read_program("day02.input.txt"), &[]);
+        compare(&read_program("day05.input.txt"), &[1]);
+        compare(&read_program("day05.input.txt"), &[5]);
+        compare(&read_program("day09.input.txt"), &[1]);
+        compare(&read_program("day09.input.txt"), &[2]);
+    }
+
+    #[test]
+    fn compiled_hands_self_modifying_code_to_interpreter() {
+        // Overwrites the output at 8 with a halt, then loops back to it.
+        let program = [1101, 0, 99, 8, 1105, 1, 8, 0, 104, 7, 99];
+
+        assert_eq!(Vec::<i64>::new(), compare(&program, &[]));
+    }
+
+    #[test]
+    fn compiled_reports_interpreter_errors() {
+        let program = [1, -1, 0, 0, 99];
+        let mut intcomp = Intcomp::new(&program);
+
+        assert_eq!(
+            Err(IntcompError::NegativeAddress {
+                address: 0,
+                target: -1
+            }),
+            Compiled::new(&program).execute(&mut intcomp)
+        );
+    }
+
+    #[test]
+    fn compiled_resumes_after_awaiting_input() {
+        let compiled = Compiled::new(&INPUT_COUNTDOWN);
+        let mut intcomp = Intcomp::new(&INPUT_COUNTDOWN);
+
+        assert_eq!(Ok(RunState::AwaitingInput), compiled.execute(&mut intcomp));
+
+        intcomp.send_input(2);
+
+        assert_eq!(Ok(RunState::Halted), compiled.execute(&mut intcomp));
+        assert_eq!(vec![2, 1], intcomp.snapshot().output);
+    }
+
+    #[test]
+    fn compiled_emits_rust() {
+        assert_eq!(
+            include_str!("compiler/countdown.rs"),
+            Compiled::new(&INPUT_COUNTDOWN).to_rust()
+        );
+    }
+
+    #[test]
+    fn emitted_rust_matches_interpreter() {
+        let mut interpreted = Intcomp::new(&INPUT_COUNTDOWN);
+        let mut intcomp = Intcomp::new(&generated::PROGRAM);
+
+        interpreted.send_input(4);
+        intcomp.send_input(4);
+
+        assert_eq!(interpreted.execute(), generated::execute(&mut intcomp));
+        assert_eq!(interpreted.snapshot(), intcomp.snapshot());
+    }
+}
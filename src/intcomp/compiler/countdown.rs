@@ -0,0 +1,56 @@
+// Compiled from an Intcode program by `aoc2019 compile`.
+
+use aoc2019::intcomp::compiler::{self, Frame};
+use aoc2019::intcomp::{Intcomp, IntcompError, RunState};
+
+pub const PROGRAM: [i64; 14] = [
+    3, 13, 4, 13, 1001, 13, -1, 13, 1005, 13,
+    2, 99, 0, 0,
+];
+
+const STARTS: [usize; 5] = [
+    0, 2, 4, 8, 11,
+];
+
+pub fn execute(intcomp: &mut Intcomp) -> Result<RunState, IntcompError> {
+    compiler::run(intcomp, &PROGRAM, &STARTS, body)
+}
+
+fn body(frame: &mut Frame) -> Option<RunState> {
+    loop {
+        match frame.ip() {
+            0 => {
+                let target = frame.target(0, 1)?;
+                match frame.input() {
+                    Some(value) => frame.store(target, value),
+                    None => return Some(RunState::AwaitingInput),
+                }
+                frame.advance(2);
+            }
+            2 => {
+                let value = frame.load(0, 3)?;
+                frame.output(value);
+                frame.advance(4);
+            }
+            4 => {
+                let value = frame.load(0, 5)?.checked_add(frame.load(1, 6)?)?;
+                let target = frame.target(0, 7)?;
+                frame.store(target, value);
+                frame.advance(8);
+            }
+            8 => {
+                let value = frame.load(0, 9)?;
+                let target = frame.load(1, 10)?;
+                if value != 0 {
+                    frame.jump(target)?;
+                } else {
+                    frame.advance(11);
+                }
+            }
+            11 => {
+                return Some(frame.halt());
+            }
+            _ => return None,
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use super::disassembler::disassemble_from;
+use super::memory::Memory;
+use super::{Hook, Intcomp, RunState};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+/// The most words the `memory` command prints at once.
+const MAX_WORDS: usize = 1024;
+
+const HELP: &str = "\
+break <address>          stop before executing the instruction at address (b)
+watch <address>          stop before anything is written to address (w)
+delete <address>         remove the breakpoint and watchpoint at address (d)
+step [count]             execute count instructions, default 1 (s)
+continue                 run until halted, awaiting input or stopped (c)
+registers                print ip and bp (r)
+memory <address> [count] print count words of memory, default 8, at most 1024 (x)
+list [address] [count]   disassemble count instructions, default from ip (l)
+input <value>...         queue values as input for the program (i)
+help                     print this message (h)
+quit                     leave the debugger (q)";
+
+/// Whether `word` is written as a whole number, however large.
+fn is_integer(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+
+    !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Why the debugger interrupted the program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stop {
+    Breakpoint(usize),
+    Watchpoint { address: usize, value: i64 },
+}
+
+/// The breakpoints and watchpoints checked while the program runs.
+#[derive(Default)]
+struct Points {
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+
+    /// Set when resuming, so that the breakpoint at the current instruction
+    /// doesn't stop it again immediately.
+    resuming: bool,
+
+    /// Set while re-executing an instruction which was stopped by a
+    /// watchpoint, for the same reason.
+    ignore_watchpoints: bool,
+
+    stop: Option<Stop>,
+}
+
+impl Hook for Points {
+    fn before_instruction(&mut self, ip: usize, _bp: i64) -> bool {
+        if self.resuming {
+            self.resuming = false;
+
+            return true;
+        }
+
+        self.ignore_watchpoints = false;
+
+        if self.breakpoints.contains(&ip) {
+            self.stop = Some(Stop::Breakpoint(ip));
+
+            return false;
+        }
+
+        true
+    }
+
+    fn before_write(&mut self, address: usize, value: i64) -> bool {
+        if !self.ignore_watchpoints && self.watchpoints.contains(&address) {
+            self.stop = Some(Stop::Watchpoint { address, value });
+
+            return false;
+        }
+
+        true
+    }
+}
+
+/// An interactive debugger which runs a program under the control of text
+/// commands.
+pub struct Debugger {
+    intcomp: Intcomp,
+    points: Points,
+}
+
+impl Debugger {
+    pub fn new(program: &[i64]) -> Debugger {
+        Debugger {
+            intcomp: Intcomp::new(program),
+            points: Points::default(),
+        }
+    }
+
+    /// Carry out a single command, returning the text to show the user or
+    /// `None` if the user asked to quit.
+    pub fn command(&mut self, line: &str) -> Option<String> {
+        let mut words = line.split_whitespace();
+
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Some(String::new()),
+        };
+
+        // `input` takes any numbers; every other command takes addresses and
+        // counts.
+        let input = matches!(command, "i" | "input");
+        let words = words.collect::<Vec<_>>();
+        let values = words.iter().map(|word| word.parse::<i64>()).collect();
+        let arguments = words.iter().map(|word| word.parse::<usize>()).collect();
+
+        let (values, arguments): (Vec<i64>, Vec<usize>) = match (values, arguments) {
+            (Ok(values), _) if input => (values, Vec::new()),
+            (_, Ok(arguments)) if !input => (Vec::new(), arguments),
+            (Ok(_), Err(_)) => return Some("Addresses and counts can't be negative.".to_string()),
+            _ if words.iter().all(|word| is_integer(word)) => {
+                return Some("Arguments are out of range.".to_string())
+            }
+
+            _ => return Some("Arguments must be numbers.".to_string()),
+        };
+
+        let argument = |index: usize| arguments.get(index).cloned();
+
+        let response = match (command, argument(0)) {
+            ("b", Some(address)) | ("break", Some(address)) => {
+                self.points.breakpoints.insert(address);
+
+                format!("Breakpoint set at {:04}.", address)
+            }
+
+            ("w", Some(address)) | ("watch", Some(address)) => {
+                self.points.watchpoints.insert(address);
+
+                format!("Watchpoint set at {:04}.", address)
+            }
+
+            ("d", Some(address)) | ("delete", Some(address)) => {
+                let breakpoint = self.points.breakpoints.remove(&address);
+                let watchpoint = self.points.watchpoints.remove(&address);
+
+                if breakpoint || watchpoint {
+                    format!("Deleted {:04}.", address)
+                } else {
+                    format!("Nothing set at {:04}.", address)
+                }
+            }
+
+            ("s", count) | ("step", count) => self.resume(Some(count.unwrap_or(1))),
+            ("c", _) | ("continue", _) => self.resume(None),
+            ("r", _) | ("registers", _) => self.registers(),
+
+            ("x", Some(address)) | ("memory", Some(address)) => {
+                self.memory(address, argument(1).unwrap_or(8))
+            }
+
+            ("l", start) | ("list", start) => {
+                self.list(start.unwrap_or(self.intcomp.ip), argument(1).unwrap_or(10))
+            }
+
+            ("i", _) | ("input", _) if !values.is_empty() => {
+                for value in &values {
+                    self.intcomp.send_input(*value);
+                }
+
+                format!("Queued {} input(s).", values.len())
+            }
+
+            ("h", _) | ("help", _) => HELP.to_string(),
+            ("q", _) | ("quit", _) => return None,
+            _ => format!("Unrecognized command '{}'; try 'help'.", line.trim()),
+        };
+
+        Some(response)
+    }
+
+    /// Read commands from `input` until it runs out or the user quits.
+    pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(output, "{}", self.list(self.intcomp.ip, 1))?;
+        write!(output, "(intcomp) ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            match self.command(&line?) {
+                Some(response) if response.is_empty() => {}
+                Some(response) => writeln!(output, "{}", response)?,
+                None => return Ok(()),
+            }
+
+            write!(output, "(intcomp) ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the program, either for `limit` instructions or until it stops by
+    /// itself, and describe where it ended up.
+    fn resume(&mut self, limit: Option<usize>) -> String {
+        self.points.ignore_watchpoints =
+            matches!(self.points.stop.take(), Some(Stop::Watchpoint { .. }));
+
+        self.points.resuming = true;
+
+        let result = match limit {
+            Some(limit) => self.intcomp.run_for_with(limit, &mut self.points),
+            None => self.intcomp.execute_with(&mut self.points),
+        };
+
+        let mut lines = Vec::new();
+        let mut outputs = Vec::new();
+
+        while let Some(output) = self.intcomp.receive_output() {
+            outputs.push(output.to_string());
+        }
+
+        if !outputs.is_empty() {
+            lines.push(format!("Output: {}", outputs.join(", ")));
+        }
+
+        let ip = self.intcomp.ip;
+
+        lines.push(match result {
+            Ok(RunState::Interrupted) => match self.points.stop {
+                Some(Stop::Breakpoint(address)) => format!("Breakpoint at {:04}.", address),
+
+                Some(Stop::Watchpoint { address, value }) => format!(
+                    "Watchpoint at {:04}: [{}] {} -> {}.",
+                    ip,
+                    address,
+                    self.read(address),
+                    value
+                ),
+
+                None => format!("Interrupted at {:04}.", ip),
+            },
+
+            Ok(RunState::Halted) => format!("Halted at {:04}.", ip),
+            Ok(RunState::AwaitingInput) => format!("Awaiting input at {:04}.", ip),
+            Ok(_) => format!("Stopped at {:04}.", ip),
+            Err(error) => format!("Error: {}.", error),
+        });
+
+        lines.push(self.list(ip, 1));
+        lines.join("\n")
+    }
+
+    fn registers(&self) -> String {
+        format!("ip: {:04}  bp: {}", self.intcomp.ip, self.intcomp.bp)
+    }
+
+    /// Unallocated memory reads as zero, just as it would to the program.
+    fn read(&self, address: usize) -> i64 {
+        self.intcomp.memory.read(address)
+    }
+
+    /// Print at most `MAX_WORDS` words, starting at `start`.
+    fn memory(&self, start: usize, count: usize) -> String {
+        let end = match start.checked_add(count.min(MAX_WORDS)) {
+            Some(end) => end,
+            None => return format!("Address {} is out of range.", start),
+        };
+
+        (start..end)
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(|addresses| {
+                let values = addresses
+                    .iter()
+                    .map(|address| format!("{:>8}", self.read(*address)))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                format!("{:04}: {}", addresses[0], values)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn list(&self, start: usize, count: usize) -> String {
+        let lines = disassemble_from(&self.intcomp.memory.image(), start)
+            .take(count)
+            .map(|instruction| {
+                let marker = if instruction.address() == self.intcomp.ip {
+                    "=>"
+                } else {
+                    "  "
+                };
+
+                format!("{} {}", marker, instruction)
+            })
+            .collect::<Vec<_>>();
+
+        if lines.is_empty() {
+            format!("No code at {:04}.", start)
+        } else {
+            lines.join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(debugger: &mut Debugger, line: &str) -> String {
+        debugger.command(line).expect("debugger quit")
+    }
+
+    #[test]
+    fn debugger_stops_at_breakpoint() {
+        let mut debugger = Debugger::new(&[1101, 1, 1, 9, 104, 7, 99]);
+
+        run(&mut debugger, "break 4");
+
+        assert_eq!(
+            "Breakpoint at 0004.\n=> 0004: OUT #7",
+            run(&mut debugger, "continue")
+        );
+        assert_eq!(2, debugger.read(9));
+    }
+
+    #[test]
+    fn debugger_continues_past_breakpoint() {
+        let mut debugger = Debugger::new(&[1101, 1, 1, 9, 104, 7, 99]);
+
+        run(&mut debugger, "b 4");
+        run(&mut debugger, "c");
+
+        assert_eq!(
+            "Output: 7\nHalted at 0006.\n=> 0006: HLT",
+            run(&mut debugger, "c")
+        );
+    }
+
+    #[test]
+    fn debugger_stops_before_watched_write() {
+        let mut debugger = Debugger::new(&[1101, 1, 1, 9, 99]);
+
+        run(&mut debugger, "watch 9");
+
+        assert_eq!(
+            "Watchpoint at 0000: [9] 0 -> 2.\n=> 0000: ADD #1, #1 -> [9]",
+            run(&mut debugger, "c")
+        );
+        assert_eq!(0, debugger.read(9));
+
+        assert_eq!("Halted at 0004.\n=> 0004: HLT", run(&mut debugger, "c"));
+        assert_eq!(2, debugger.read(9));
+    }
+
+    #[test]
+    fn debugger_deletes_points() {
+        let mut debugger = Debugger::new(&[1101, 1, 1, 9, 99]);
+
+        run(&mut debugger, "watch 9");
+        run(&mut debugger, "break 4");
+
+        assert_eq!("Deleted 0009.", run(&mut debugger, "delete 9"));
+        assert_eq!("Deleted 0004.", run(&mut debugger, "d 4"));
+        assert_eq!("Nothing set at 0004.", run(&mut debugger, "d 4"));
+        assert_eq!("Halted at 0004.\n=> 0004: HLT", run(&mut debugger, "c"));
+    }
+
+    #[test]
+    fn debugger_steps() {
+        let mut debugger = Debugger::new(&[109, 5, 1101, 1, 1, 9, 99]);
+
+        assert_eq!(
+            "Stopped at 0002.\n=> 0002: ADD #1, #1 -> [9]",
+            run(&mut debugger, "step")
+        );
+        assert_eq!("ip: 0002  bp: 5", run(&mut debugger, "registers"));
+        assert_eq!("Halted at 0006.\n=> 0006: HLT", run(&mut debugger, "s 5"));
+    }
+
+    #[test]
+    fn debugger_injects_input() {
+        let mut debugger = Debugger::new(&[3, 7, 4, 7, 99]);
+
+        assert_eq!(
+            "Awaiting input at 0000.\n=> 0000: IN -> [7]",
+            run(&mut debugger, "c")
+        );
+        assert_eq!("Queued 1 input(s).", run(&mut debugger, "input 42"));
+        assert_eq!(
+            "Output: 42\nHalted at 0004.\n=> 0004: HLT",
+            run(&mut debugger, "c")
+        );
+    }
+
+    #[test]
+    fn debugger_prints_memory() {
+        let mut debugger = Debugger::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+        assert_eq!(
+            "0008:        9       10        0",
+            run(&mut debugger, "memory 8 3")
+        );
+    }
+
+    #[test]
+    fn debugger_lists_code() {
+        let mut debugger = Debugger::new(&[104, 1, 104, 2, 99]);
+
+        assert_eq!(
+            "=> 0000: OUT #1\n   0002: OUT #2\n   0004: HLT",
+            run(&mut debugger, "list")
+        );
+    }
+
+    #[test]
+    fn debugger_reports_errors() {
+        let mut debugger = Debugger::new(&[0]);
+
+        assert_eq!(
+            "Error: invalid opcode 0 at address 0.\n=> 0000: DATA 0",
+            run(&mut debugger, "c")
+        );
+    }
+
+    #[test]
+    fn debugger_rejects_bad_commands() {
+        let mut debugger = Debugger::new(&[99]);
+
+        assert_eq!("Arguments must be numbers.", run(&mut debugger, "break x"));
+        assert_eq!(
+            "Addresses and counts can't be negative.",
+            run(&mut debugger, "x 0 -1")
+        );
+        assert_eq!(
+            format!("Address {} is out of range.", usize::MAX),
+            run(&mut debugger, &format!("x {} 2", usize::MAX))
+        );
+        assert_eq!(
+            "Arguments are out of range.",
+            run(&mut debugger, "x 0 99999999999999999999999")
+        );
+        assert_eq!(
+            MAX_WORDS / 8,
+            run(&mut debugger, &format!("x 0 {}", i64::MAX))
+                .lines()
+                .count()
+        );
+        assert_eq!(
+            "Unrecognized command 'frobnicate'; try 'help'.",
+            run(&mut debugger, "frobnicate")
+        );
+        assert_eq!(None, debugger.command("quit"));
+    }
+
+    #[test]
+    fn debugger_repl_reads_commands() {
+        let mut debugger = Debugger::new(&[104, 1, 99]);
+        let mut output = Vec::new();
+
+        debugger
+            .repl(&b"c\nq\n"[..], &mut output)
+            .expect("repl failed");
+
+        assert_eq!(
+            "=> 0000: OUT #1\n(intcomp) Output: 1\nHalted at 0002.\n=> 0002: HLT\n(intcomp) ",
+            String::from_utf8(output).expect("invalid utf-8")
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use super::{Instruction, IntcompError};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// How a custom instruction uses one of its operands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OperandKind {
+    /// Read in any mode and passed to the instruction's semantics.
+    Value,
+
+    /// Written with one of the values the semantics return.  Like the targets
+    /// of built-in instructions, it can't be in immediate mode.
+    Target,
+}
+
+/// What a custom instruction does once its operands have been read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    /// Write one value to each `Target` operand, in order, and move on to the
+    /// next instruction.
+    Continue(Vec<i64>),
+
+    /// Jump to the given address.
+    Jump(i64),
+
+    /// Stop as `Halt` does.
+    Halt,
+}
+
+type Semantics = dyn Fn(&[i64]) -> Effect + Send + Sync;
+
+/// Why `Dialect::define` refused an instruction.
+#[derive(Debug, PartialEq)]
+pub enum DialectError {
+    /// Opcodes are the last two digits of an instruction, so must be between
+    /// 0 and 99.
+    InvalidOpcode { opcode: i64 },
+
+    /// The mode digits for more than 16 operands wouldn't fit in an i64.
+    TooManyOperands { opcode: i64, count: usize },
+}
+
+impl fmt::Display for DialectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DialectError::InvalidOpcode { opcode } => {
+                write!(f, "opcode {} is not between 0 and 99", opcode)
+            }
+
+            DialectError::TooManyOperands { opcode, count } => write!(
+                f,
+                "opcode {} has {} operands, but at most 16 are allowed",
+                opcode, count
+            ),
+        }
+    }
+}
+
+impl Error for DialectError {}
+
+/// An instruction defined outside `intcomp`.
+#[derive(Clone)]
+pub(super) struct CustomOpcode {
+    pub(super) operands: Vec<OperandKind>,
+    semantics: Arc<Semantics>,
+}
+
+impl CustomOpcode {
+    /// The number of targets among the operands.
+    pub(super) fn target_count(&self) -> usize {
+        self.operands
+            .iter()
+            .filter(|kind| **kind == OperandKind::Target)
+            .count()
+    }
+
+    /// Apply the instruction to the values of its `Value` operands.
+    pub(super) fn apply(&self, values: &[i64]) -> Effect {
+        (self.semantics)(values)
+    }
+}
+
+impl fmt::Debug for CustomOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomOpcode")
+            .field("operands", &self.operands)
+            .finish()
+    }
+}
+
+/// An instruction as a dialect decodes it.
+pub(super) enum Decoded<'a> {
+    Builtin(Instruction),
+    Custom(&'a CustomOpcode),
+}
+
+/// The set of opcodes a machine understands: some or all of the built-in
+/// ones, plus any custom opcodes.  Machines use the full built-in set unless
+/// given a dialect with `Intcomp::set_dialect`, and anything outside their
+/// dialect fails with `InvalidOpcode`.
+#[derive(Clone, Debug)]
+pub struct Dialect {
+    builtins: BTreeSet<i64>,
+    custom: BTreeMap<i64, CustomOpcode>,
+
+    /// The highest operand mode digit allowed.
+    max_mode: i64,
+}
+
+impl Dialect {
+    /// Every built-in opcode, as of the relative base being added on Day 9.
+    pub fn full() -> Dialect {
+        Dialect {
+            builtins: (1..=9).chain(Some(99)).collect(),
+            custom: BTreeMap::new(),
+            max_mode: 2,
+        }
+    }
+
+    /// Add, multiply and halt, with every operand in position mode, as
+    /// introduced on Day 2.
+    pub fn day2() -> Dialect {
+        Dialect {
+            builtins: [1, 2, 99].iter().cloned().collect(),
+            custom: BTreeMap::new(),
+            max_mode: 0,
+        }
+    }
+
+    /// Day 2's opcodes plus I/O, jumps and comparisons, with position and
+    /// immediate modes, as of Day 5.  Relative mode arrived with opcode 9, so
+    /// it's rejected too.
+    pub fn day5() -> Dialect {
+        Dialect {
+            builtins: (1..=8).chain(Some(99)).collect(),
+            custom: BTreeMap::new(),
+            max_mode: 1,
+        }
+    }
+
+    /// Add an instruction with the given opcode, which replaces any built-in
+    /// or custom instruction with the same opcode.  The mode of each operand
+    /// comes from the instruction's digits, as for built-in instructions.
+    ///
+    /// `semantics` is given the values of the `Value` operands, in order.
+    pub fn define<F>(
+        &mut self,
+        opcode: i64,
+        operands: &[OperandKind],
+        semantics: F,
+    ) -> Result<(), DialectError>
+    where
+        F: Fn(&[i64]) -> Effect + Send + Sync + 'static,
+    {
+        if !(0..100).contains(&opcode) {
+            return Err(DialectError::InvalidOpcode { opcode });
+        }
+
+        if operands.len() > 16 {
+            return Err(DialectError::TooManyOperands {
+                opcode,
+                count: operands.len(),
+            });
+        }
+
+        self.builtins.remove(&opcode);
+        self.custom.insert(
+            opcode,
+            CustomOpcode {
+                operands: operands.to_vec(),
+                semantics: Arc::new(semantics),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Stop understanding `opcode`, whether built-in or custom.
+    pub fn remove(&mut self, opcode: i64) {
+        self.builtins.remove(&opcode);
+        self.custom.remove(&opcode);
+    }
+
+    /// `address` is only used to report where a malformed instruction was found.
+    pub(super) fn decode(&self, word: i64, address: usize) -> Result<Decoded<'_>, IntcompError> {
+        let opcode = word % 100;
+        let mut modes = word / 100;
+
+        while modes > 0 {
+            if modes % 10 > self.max_mode {
+                return Err(IntcompError::InvalidMode {
+                    address,
+                    digit: (modes % 10) as u8,
+                });
+            }
+
+            modes /= 10;
+        }
+
+        if let Some(custom) = self.custom.get(&opcode) {
+            return Ok(Decoded::Custom(custom));
+        }
+
+        if !self.builtins.contains(&opcode) {
+            return Err(IntcompError::InvalidOpcode {
+                address,
+                opcode: word,
+            });
+        }
+
+        Instruction::decode(word, address).map(Decoded::Builtin)
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect::full()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::{Intcomp, RunState};
+
+    fn run(program: &[i64], dialect: Dialect) -> (Intcomp, Result<RunState, IntcompError>) {
+        let mut intcomp = Intcomp::new(program);
+
+        intcomp.set_dialect(dialect);
+
+        let state = intcomp.execute();
+
+        (intcomp, state)
+    }
+
+    #[test]
+    fn day2_dialect_runs_day2_programs() {
+        let (intcomp, state) = run(&[1, 0, 0, 0, 2, 0, 0, 0, 99], Dialect::day2());
+
+        assert_eq!(Ok(RunState::Halted), state);
+        assert_eq!(4, intcomp.read_memory(0));
+    }
+
+    #[test]
+    fn day2_dialect_rejects_newer_opcodes() {
+        let (_, state) = run(&[1, 0, 0, 0, 4, 0, 99], Dialect::day2());
+
+        assert_eq!(
+            Err(IntcompError::InvalidOpcode {
+                address: 4,
+                opcode: 4
+            }),
+            state
+        );
+    }
+
+    #[test]
+    fn day2_dialect_rejects_parameter_modes() {
+        let (_, state) = run(&[10001, 0, 0, 0, 99], Dialect::day2());
+
+        assert_eq!(
+            Err(IntcompError::InvalidMode {
+                address: 0,
+                digit: 1
+            }),
+            state
+        );
+    }
+
+    #[test]
+    fn day5_dialect_rejects_relative_base() {
+        let (_, state) = run(&[109, 1, 99], Dialect::day5());
+
+        assert_eq!(
+            Err(IntcompError::InvalidOpcode {
+                address: 0,
+                opcode: 109
+            }),
+            state
+        );
+    }
+
+    #[test]
+    fn day5_dialect_rejects_relative_mode() {
+        let (_, state) = run(&[204, 0, 99], Dialect::day5());
+
+        assert_eq!(
+            Err(IntcompError::InvalidMode {
+                address: 0,
+                digit: 2
+            }),
+            state
+        );
+    }
+
+    #[test]
+    fn dialect_rejects_invalid_definitions() {
+        let mut dialect = Dialect::full();
+
+        assert_eq!(
+            Err(DialectError::InvalidOpcode { opcode: 100 }),
+            dialect.define(100, &[], |_| Effect::Halt)
+        );
+        assert_eq!(
+            Err(DialectError::TooManyOperands {
+                opcode: 10,
+                count: 17
+            }),
+            dialect.define(10, &[OperandKind::Value; 17], |_| Effect::Halt)
+        );
+    }
+
+    #[test]
+    fn dialect_reports_wrong_result_count() {
+        let mut dialect = Dialect::full();
+
+        dialect
+            .define(10, &[OperandKind::Target], |_| Effect::Continue(vec![]))
+            .expect("definition failed");
+
+        let (_, state) = run(&[10, 0, 99], dialect);
+
+        assert_eq!(
+            Err(IntcompError::WrongResultCount {
+                address: 0,
+                expected: 1,
+                found: 0
+            }),
+            state
+        );
+    }
+
+    #[test]
+    fn dialect_supports_custom_opcodes() {
+        let mut dialect = Dialect::full();
+
+        dialect
+            .define(
+                10,
+                &[OperandKind::Value, OperandKind::Value, OperandKind::Target],
+                |values| Effect::Continue(vec![values[0] - values[1]]),
+            )
+            .expect("definition failed");
+
+        // SUB #9, #4 -> [0], then the built-in output.
+        let (mut intcomp, state) = run(&[1110, 9, 4, 0, 4, 0, 99], dialect);
+
+        assert_eq!(Ok(RunState::Halted), state);
+        assert_eq!(Some(5), intcomp.receive_output());
+    }
+
+    #[test]
+    fn dialect_supports_custom_jumps_and_halts() {
+        let mut dialect = Dialect::full();
+
+        dialect
+            .define(20, &[OperandKind::Value], |values| Effect::Jump(values[0]))
+            .expect("definition failed");
+        dialect
+            .define(21, &[], |_| Effect::Halt)
+            .expect("definition failed");
+
+        // Jumps over an output to a custom halt.
+        let (mut intcomp, state) = run(&[120, 4, 104, 1, 21, 99], dialect);
+
+        assert_eq!(Ok(RunState::Halted), state);
+        assert_eq!(None, intcomp.receive_output());
+    }
+
+    #[test]
+    fn dialect_rejects_immediate_custom_targets() {
+        let mut dialect = Dialect::full();
+
+        dialect
+            .define(10, &[OperandKind::Target], |_| Effect::Continue(vec![1]))
+            .expect("definition failed");
+
+        let (_, state) = run(&[110, 0, 99], dialect);
+
+        assert_eq!(Err(IntcompError::ImmediateWrite { address: 0 }), state);
+    }
+
+    #[test]
+    fn dialect_can_remove_opcodes() {
+        let mut dialect = Dialect::full();
+
+        dialect.remove(99);
+
+        let (_, state) = run(&[99], dialect);
+
+        assert_eq!(
+            Err(IntcompError::InvalidOpcode {
+                address: 0,
+                opcode: 99
+            }),
+            state
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use super::{Instruction, OperandMode};
+use std::fmt;
+
+/// A single operand of a disassembled instruction, along with its raw value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Operand {
+    fn new(mode: OperandMode, value: i64) -> Operand {
+        match mode {
+            OperandMode::Position => Operand::Position(value),
+            OperandMode::Immediate => Operand::Immediate(value),
+            OperandMode::Relative => Operand::Relative(value),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Position(address) => write!(f, "[{}]", address),
+            Operand::Immediate(value) => write!(f, "#{}", value),
+            Operand::Relative(offset) if *offset < 0 => write!(f, "[rb{}]", offset),
+            Operand::Relative(offset) => write!(f, "[rb+{}]", offset),
+        }
+    }
+}
+
+/// One line of a listing: either a decoded instruction or a word of memory
+/// which doesn't decode as one.
+#[derive(Debug, PartialEq)]
+pub enum DisassembledInstruction {
+    Instruction {
+        address: usize,
+        mnemonic: &'static str,
+        operands: Vec<Operand>,
+        target: Option<Operand>,
+    },
+
+    Data {
+        address: usize,
+        value: i64,
+    },
+}
+
+impl DisassembledInstruction {
+    pub fn address(&self) -> usize {
+        match self {
+            DisassembledInstruction::Instruction { address, .. } => *address,
+            DisassembledInstruction::Data { address, .. } => *address,
+        }
+    }
+
+    /// The number of words of memory this line covers.
+    pub fn word_count(&self) -> usize {
+        match self {
+            DisassembledInstruction::Instruction {
+                operands, target, ..
+            } => 1 + operands.len() + target.iter().count(),
+
+            DisassembledInstruction::Data { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisassembledInstruction::Instruction {
+                address,
+                mnemonic,
+                operands,
+                target,
+            } => {
+                write!(f, "{:04}: {}", address, mnemonic)?;
+
+                if !operands.is_empty() {
+                    let operands = operands
+                        .iter()
+                        .map(|operand| operand.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    write!(f, " {}", operands)?;
+                }
+
+                if let Some(target) = target {
+                    write!(f, " -> {}", target)?;
+                }
+
+                Ok(())
+            }
+
+            DisassembledInstruction::Data { address, value } => {
+                write!(f, "{:04}: DATA {}", address, value)
+            }
+        }
+    }
+}
+
+/// Split an instruction into its mnemonic, the modes of the operands it reads
+/// and the mode of the operand it writes (if any).
+fn describe(instruction: Instruction) -> (&'static str, Vec<OperandMode>, Option<OperandMode>) {
+    match instruction {
+        Instruction::Add(operand1, operand2, target) => {
+            ("ADD", vec![operand1, operand2], Some(target))
+        }
+
+        Instruction::Multiply(operand1, operand2, target) => {
+            ("MUL", vec![operand1, operand2], Some(target))
+        }
+
+        Instruction::Input(target) => ("IN", vec![], Some(target)),
+        Instruction::Output(operand) => ("OUT", vec![operand], None),
+        Instruction::JumpIfTrue(operand, jump_to) => ("JT", vec![operand, jump_to], None),
+        Instruction::JumpIfFalse(operand, jump_to) => ("JF", vec![operand, jump_to], None),
+
+        Instruction::LessThan(operand1, operand2, target) => {
+            ("LT", vec![operand1, operand2], Some(target))
+        }
+
+        Instruction::Equals(operand1, operand2, target) => {
+            ("EQ", vec![operand1, operand2], Some(target))
+        }
+
+        Instruction::AdjustRelativeBase(operand) => ("ARB", vec![operand], None),
+        Instruction::Halt => ("HLT", vec![], None),
+    }
+}
+
+/// Decode the instruction at `address`, if there is a valid one there which
+/// fits entirely within the program.
+fn decode(program: &[i64], address: usize) -> Option<DisassembledInstruction> {
+    let instruction = Instruction::parse(program[address], address).ok()?;
+    let (mnemonic, operand_modes, target_mode) = describe(instruction);
+    let word_count = 1 + operand_modes.len() + target_mode.iter().count();
+
+    if address + word_count > program.len() {
+        return None;
+    }
+
+    // Mode digits for operands the instruction doesn't have can't be
+    // represented in the listing, so such words are shown as data instead.
+    if program[address] / i64::pow(10, word_count as u32 + 1) != 0 {
+        return None;
+    }
+
+    let mut words = program[address + 1..address + word_count].iter();
+
+    let operands = operand_modes
+        .into_iter()
+        .zip(&mut words)
+        .map(|(mode, value)| Operand::new(mode, *value))
+        .collect();
+
+    let target = match target_mode {
+        Some(OperandMode::Immediate) => return None,
+        Some(mode) => Some(Operand::new(mode, *words.next()?)),
+        None => None,
+    };
+
+    Some(DisassembledInstruction::Instruction {
+        address,
+        mnemonic,
+        operands,
+        target,
+    })
+}
+
+/// Iterator over the lines of a listing, produced by `disassemble_from`.
+pub struct Disassembly<'a> {
+    program: &'a [i64],
+    address: usize,
+}
+
+impl<'a> Iterator for Disassembly<'a> {
+    type Item = DisassembledInstruction;
+
+    fn next(&mut self) -> Option<DisassembledInstruction> {
+        if self.address >= self.program.len() {
+            return None;
+        }
+
+        let address = self.address;
+
+        let instruction = decode(self.program, address).unwrap_or(DisassembledInstruction::Data {
+            address,
+            value: self.program[address],
+        });
+
+        self.address += instruction.word_count();
+
+        Some(instruction)
+    }
+}
+
+/// Decode an entire program from start to finish.  Words which can't be
+/// decoded as an instruction are listed as data and skipped one at a time.
+pub fn disassemble(program: &[i64]) -> Vec<DisassembledInstruction> {
+    disassemble_from(program, 0).collect()
+}
+
+/// Decode a program lazily, starting at `address` rather than the beginning.
+pub fn disassemble_from(program: &[i64], address: usize) -> Disassembly<'_> {
+    Disassembly { program, address }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(program: &[i64]) -> Vec<String> {
+        disassemble(program)
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn disassemble_formats_operand_modes() {
+        assert_eq!(
+            vec!["0000: ADD [rb+3], #5 -> [100]"],
+            listing(&[1201, 3, 5, 100])
+        );
+    }
+
+    #[test]
+    fn disassemble_formats_negative_relative_operand() {
+        assert_eq!(vec!["0000: OUT [rb-1]"], listing(&[204, -1]));
+    }
+
+    #[test]
+    fn disassemble_supports_every_instruction() {
+        let program = vec![
+            1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 4, 0, 5, 0, 0, 6, 0, 0, 7, 0, 0, 0, 8, 0, 0, 0, 9, 0, 99,
+        ];
+
+        assert_eq!(
+            vec![
+                "0000: ADD [0], [0] -> [0]",
+                "0004: MUL [0], [0] -> [0]",
+                "0008: IN -> [0]",
+                "0010: OUT [0]",
+                "0012: JT [0], [0]",
+                "0015: JF [0], [0]",
+                "0018: LT [0], [0] -> [0]",
+                "0022: EQ [0], [0] -> [0]",
+                "0026: ARB [0]",
+                "0028: HLT",
+            ],
+            listing(&program)
+        );
+    }
+
+    #[test]
+    fn disassemble_lists_invalid_opcodes_as_data() {
+        assert_eq!(
+            vec!["0000: HLT", "0001: DATA 0", "0002: DATA 1234"],
+            listing(&[99, 0, 1234])
+        );
+    }
+
+    #[test]
+    fn disassemble_lists_immediate_targets_as_data() {
+        assert_eq!(
+            vec![
+                "0000: DATA 11101",
+                "0001: DATA 0",
+                "0002: DATA 0",
+                "0003: DATA 0"
+            ],
+            listing(&[11101, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn disassemble_lists_extra_mode_digits_as_data() {
+        assert_eq!(vec!["0000: DATA 10099"], listing(&[10099]));
+    }
+
+    #[test]
+    fn disassemble_lists_truncated_instructions_as_data() {
+        assert_eq!(
+            vec!["0000: HLT", "0001: DATA 1", "0002: DATA 0"],
+            listing(&[99, 1, 0])
+        );
+    }
+
+    #[test]
+    fn disassemble_from_starts_at_address() {
+        let lines = disassemble_from(&[99, 104, 1, 99], 1)
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["0001: OUT #1", "0003: HLT"], lines);
+    }
+
+    #[test]
+    fn disassemble_covers_every_word() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+
+        let word_count = disassemble(&program)
+            .iter()
+            .map(|instruction| instruction.word_count())
+            .sum::<usize>();
+
+        assert_eq!(program.len(), word_count);
+    }
+}
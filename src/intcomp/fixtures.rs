@@ -0,0 +1,12 @@
+//! Small programs shared by the tests of several modules.
+
+/// Counts [15] down from 3, outputting each value, then jumps over the output
+/// at 12 to halt.
+pub const COUNTDOWN: [i64; 16] = [
+    4, 15, 1001, 15, -1, 15, 1005, 15, 0, 1105, 1, 14, 104, 0, 99, 3,
+];
+
+/// Outputs each input doubled until it reads a zero.
+pub const DOUBLER: [i64; 16] = [
+    3, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0,
+];
@@ -0,0 +1,169 @@
+use super::analysis::Analysis;
+use super::memory::Memory;
+use super::snapshot::Snapshot;
+use super::Intcomp;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+
+/// A memory cell which differs between two states of a machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change {
+    pub address: usize,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}: {} -> {}", self.address, self.before, self.after)
+    }
+}
+
+impl Snapshot {
+    /// The cells whose values differ in `later`, in address order.  Only
+    /// words which are nonzero in either snapshot are compared, so this costs
+    /// as much as the memory the snapshots hold rather than their length.
+    pub fn diff(&self, later: &Snapshot) -> Vec<Change> {
+        let mut addresses = self
+            .memory
+            .nonzero()
+            .into_iter()
+            .chain(later.memory.nonzero())
+            .map(|(address, _)| address)
+            .collect::<Vec<_>>();
+
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        addresses
+            .into_iter()
+            .map(|address| Change {
+                address,
+                before: self.memory.read(address),
+                after: later.memory.read(address),
+            })
+            .filter(|change| change.before != change.after)
+            .collect()
+    }
+}
+
+impl<M: Memory<Word = i64>> Intcomp<M> {
+    /// The addresses of the program and the data around it, as given by
+    /// `Memory::image`, leaving out anything written far beyond them.
+    pub fn image_range(&self) -> Range<usize> {
+        0..self.memory.image().len()
+    }
+
+    /// Print the words in `range`, eight to a line, marking the one at `ip`
+    /// with `>`, the one at `bp` with `@`, and the start of each instruction
+    /// reachable from address zero with `*`.
+    pub fn dump(&self, range: Range<usize>) -> String {
+        let memory = self.memory.image();
+
+        let instructions = Analysis::new(&memory)
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions.clone())
+            .collect::<BTreeSet<_>>();
+
+        let mut lines = vec![format!(
+            "ip: {:04}  bp: {}  (> ip, @ bp, * instruction)",
+            self.ip, self.bp
+        )];
+
+        let addresses = range.collect::<Vec<_>>();
+
+        for row in addresses.chunks(8) {
+            let cells = row
+                .iter()
+                .map(|address| {
+                    let marker = if *address == self.ip {
+                        '>'
+                    } else if *address as i64 == self.bp {
+                        '@'
+                    } else if instructions.contains(address) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+
+                    format!("{}{:>8}", marker, self.memory.read(*address))
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            lines.push(format!("{:04}: {}", row[0], cells));
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intcomp_dump_marks_registers_and_instructions() {
+        let mut intcomp = Intcomp::new(&[109, 9, 104, 7, 3, 0, 99, 0, 0, 5]);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(
+            "ip: 0004  bp: 9  (> ip, @ bp, * instruction)\n\
+             0000: *     109         9 *     104         7 >       3         0 *      99         0\n\
+             0008:         0 @       5",
+            intcomp.dump(0..10)
+        );
+    }
+
+    #[test]
+    fn intcomp_image_range_leaves_out_distant_writes() {
+        // Writes 5 to [1000000] and halts.
+        let mut intcomp = Intcomp::new(&[1101, 2, 3, 1_000_000, 99]);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(1_000_001, intcomp.memory_len());
+        assert_eq!(0..5, intcomp.image_range());
+    }
+
+    #[test]
+    fn snapshot_diff_lists_changed_cells() {
+        // Writes 5 to [13], writes 0 over the 0 in [14], then writes 2 past
+        // the end of the program.
+        let mut intcomp = Intcomp::new(&[1101, 2, 3, 13, 1101, 0, 0, 14, 1101, 1, 1, 16, 99, 0, 0]);
+        let before = intcomp.snapshot();
+
+        intcomp.execute().expect("execution failed");
+
+        let changes = before.diff(&intcomp.snapshot());
+
+        assert_eq!(
+            vec![
+                Change {
+                    address: 13,
+                    before: 0,
+                    after: 5
+                },
+                Change {
+                    address: 16,
+                    before: 0,
+                    after: 2
+                },
+            ],
+            changes
+        );
+        assert_eq!("0013: 0 -> 5", changes[0].to_string());
+    }
+
+    #[test]
+    fn intcomp_try_read_memory_stops_at_end() {
+        let intcomp = Intcomp::new(&[99, 5]);
+
+        assert_eq!(Some(5), intcomp.try_read_memory(1));
+        assert_eq!(None, intcomp.try_read_memory(2));
+        assert_eq!(0, intcomp.read_memory(2));
+    }
+}
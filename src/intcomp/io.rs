@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Where an `Intcomp` gets its input and sends its output when run with
+/// `execute_io`.  Inputs are only requested once the machine's own input
+/// queue is empty.
+pub trait IntcodeIo<W = i64> {
+    /// Supply the next input, or `None` to make the machine stop and return
+    /// `AwaitingInput`.
+    fn input(&mut self) -> Option<W>;
+
+    fn output(&mut self, value: W);
+
+    /// The number of outputs sent but not yet consumed, which is what
+    /// `Intcomp::set_output_limit` limits, or `None` if outputs are passed
+    /// straight on.  The machine then counts every output it has produced.
+    fn queued_outputs(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// A pair of queues, which is how `Intcomp` handles I/O by default.
+#[derive(Clone, Debug, Default)]
+pub struct Queues<W = i64> {
+    pub input: VecDeque<W>,
+    pub output: VecDeque<W>,
+}
+
+impl<W> IntcodeIo<W> for Queues<W> {
+    fn input(&mut self) -> Option<W> {
+        self.input.pop_front()
+    }
+
+    fn output(&mut self, value: W) {
+        self.output.push_back(value);
+    }
+
+    fn queued_outputs(&self) -> Option<usize> {
+        Some(self.output.len())
+    }
+}
+
+/// Reads one number per line from stdin and writes one per line to stdout.
+/// Lines which aren't numbers are skipped; the end of stdin means no more
+/// input.
+pub struct StdIo;
+
+impl<W: FromStr + Display> IntcodeIo<W> for StdIo {
+    fn input(&mut self) -> Option<W> {
+        let stdin = io::stdin();
+
+        for line in stdin.lock().lines() {
+            match line.ok()?.trim().parse() {
+                Ok(value) => return Some(value),
+                Err(_) => eprintln!("not a number, try again"),
+            }
+        }
+
+        None
+    }
+
+    fn output(&mut self, value: W) {
+        println!("{}", value);
+    }
+}
+
+/// Calls `input` for each input and `output` with each output.
+pub struct FnIo<I, O> {
+    input: I,
+    output: O,
+}
+
+impl<I, O> FnIo<I, O> {
+    pub fn new(input: I, output: O) -> FnIo<I, O> {
+        FnIo { input, output }
+    }
+}
+
+impl<W, I: FnMut() -> Option<W>, O: FnMut(W)> IntcodeIo<W> for FnIo<I, O> {
+    fn input(&mut self) -> Option<W> {
+        (self.input)()
+    }
+
+    fn output(&mut self, value: W) {
+        (self.output)(value)
+    }
+}
+
+/// Connects a machine to channels, typically so it can run on its own
+/// thread.  Waiting for input blocks until a value arrives; once every
+/// sender has gone, the machine stops as if it were awaiting input.
+pub struct ChannelIo<W = i64> {
+    input: Receiver<W>,
+    output: Sender<W>,
+}
+
+impl<W> ChannelIo<W> {
+    pub fn new(input: Receiver<W>, output: Sender<W>) -> ChannelIo<W> {
+        ChannelIo { input, output }
+    }
+}
+
+impl<W> IntcodeIo<W> for ChannelIo<W> {
+    fn input(&mut self) -> Option<W> {
+        self.input.recv().ok()
+    }
+
+    fn output(&mut self, value: W) {
+        // Nobody listening isn't the program's problem.
+        let _ = self.output.send(value);
+    }
+}
+
+/// Plays back a fixed sequence of inputs and records every output.
+#[derive(Clone, Debug, Default)]
+pub struct Tape<W = i64> {
+    inputs: VecDeque<W>,
+    outputs: Vec<W>,
+}
+
+impl<W: Clone> Tape<W> {
+    pub fn new(inputs: &[W]) -> Tape<W> {
+        Tape {
+            inputs: inputs.iter().cloned().collect(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// The inputs which haven't been played back yet.
+    pub fn remaining(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn outputs(&self) -> &[W] {
+        &self.outputs
+    }
+}
+
+impl<W> IntcodeIo<W> for Tape<W> {
+    fn input(&mut self) -> Option<W> {
+        self.inputs.pop_front()
+    }
+
+    fn output(&mut self, value: W) {
+        self.outputs.push(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::fixtures::DOUBLER;
+    use crate::intcomp::{Intcomp, RunState};
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn tape_plays_inputs_and_records_outputs() {
+        let mut intcomp = Intcomp::new(&DOUBLER);
+        let mut tape = Tape::new(&[1, 2, 3, 0]);
+
+        assert_eq!(Ok(RunState::Halted), intcomp.execute_io(&mut tape));
+        assert_eq!(&[2, 4, 6], tape.outputs());
+        assert_eq!(0, tape.remaining());
+    }
+
+    #[test]
+    fn intcomp_awaits_input_when_io_has_none() {
+        let mut intcomp = Intcomp::new(&DOUBLER);
+        let mut tape = Tape::new(&[5]);
+
+        assert_eq!(Ok(RunState::AwaitingInput), intcomp.execute_io(&mut tape));
+        assert_eq!(&[10], tape.outputs());
+    }
+
+    #[test]
+    fn intcomp_prefers_queued_input() {
+        let mut intcomp = Intcomp::new(&DOUBLER);
+        let mut queues = Queues::default();
+
+        intcomp.send_input(4);
+        queues.input.push_back(0);
+
+        assert_eq!(Ok(RunState::Halted), intcomp.execute_io(&mut queues));
+        assert_eq!(vec![8], Vec::from(queues.output));
+    }
+
+    #[test]
+    fn fn_io_calls_closures() {
+        let mut intcomp = Intcomp::new(&DOUBLER);
+        let mut inputs = vec![0, 7];
+        let mut outputs = Vec::new();
+
+        intcomp
+            .execute_io(&mut FnIo::new(|| inputs.pop(), |value| outputs.push(value)))
+            .expect("execution failed");
+
+        assert_eq!(vec![14], outputs);
+    }
+
+    #[test]
+    fn channel_io_runs_on_another_thread() {
+        let (input, receiver) = mpsc::channel();
+        let (sender, output) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            Intcomp::new(&DOUBLER).execute_io(&mut ChannelIo::new(receiver, sender))
+        });
+
+        input.send(21).expect("send failed");
+        assert_eq!(Ok(42), output.recv());
+
+        input.send(0).expect("send failed");
+        assert_eq!(
+            Ok(RunState::Halted),
+            handle.join().expect("thread panicked")
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use super::varint::{read_words, write_words};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+const MAGIC: &[u8; 4] = b"ICP1";
+
+/// Everything which can be wrong with a program's text.  Each variant records
+/// the (1-based) line and column at which the problem was found.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    InvalidNumber {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+
+    /// A comma with no number before or after it.
+    MissingNumber { line: usize, column: usize },
+
+    /// Two numbers with no comma between them.
+    MissingComma { line: usize, column: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber {
+                line,
+                column,
+                token,
+            } => write!(
+                f,
+                "line {}, column {}: '{}' is not a number",
+                line, column, token
+            ),
+
+            ParseError::MissingNumber { line, column } => {
+                write!(f, "line {}, column {}: expected a number", line, column)
+            }
+
+            ParseError::MissingComma { line, column } => {
+                write!(f, "line {}, column {}: expected a comma", line, column)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> LoadError {
+        LoadError::Io(error)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(error: ParseError) -> LoadError {
+        LoadError::Parse(error)
+    }
+}
+
+/// Parse a program written as comma-separated numbers.  Any amount of
+/// whitespace, including newlines, may surround the numbers, and `;` starts a
+/// comment which runs to the end of the line.
+pub fn parse_program(text: &str) -> Result<Vec<i64>, ParseError> {
+    let mut program = Vec::new();
+    let mut expecting_number = true;
+    let mut last_comma = None;
+
+    for (index, text) in text.lines().enumerate() {
+        let line = index + 1;
+        let text = text.split(';').next().unwrap_or("");
+        let mut chars = text.char_indices().peekable();
+        let mut column = 0;
+
+        while let Some((start, c)) = chars.next() {
+            column += 1;
+
+            if c.is_whitespace() {
+                continue;
+            }
+
+            if c == ',' {
+                if expecting_number {
+                    return Err(ParseError::MissingNumber { line, column });
+                }
+
+                expecting_number = true;
+                last_comma = Some((line, column));
+
+                continue;
+            }
+
+            if !expecting_number {
+                return Err(ParseError::MissingComma { line, column });
+            }
+
+            let token_column = column;
+            let mut end = start + c.len_utf8();
+
+            while let Some((offset, c)) = chars.peek().cloned() {
+                if c.is_whitespace() || c == ',' {
+                    break;
+                }
+
+                column += 1;
+                end = offset + c.len_utf8();
+                chars.next();
+            }
+
+            let token = &text[start..end];
+
+            program.push(token.parse().map_err(|_| ParseError::InvalidNumber {
+                line,
+                column: token_column,
+                token: token.to_string(),
+            })?);
+
+            expecting_number = false;
+        }
+    }
+
+    match last_comma {
+        Some((line, column)) if expecting_number => Err(ParseError::MissingNumber { line, column }),
+        _ => Ok(program),
+    }
+}
+
+/// Read a program in either text or binary form, telling them apart by the
+/// binary form's header.
+pub fn read_program_from<R: Read>(reader: &mut R) -> Result<Vec<i64>, LoadError> {
+    let mut bytes = Vec::new();
+
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.starts_with(MAGIC) {
+        return Ok(read_words(&mut &bytes[MAGIC.len()..])?);
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(parse_program(&text)?),
+
+        Err(_) => Err(LoadError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "program is neither text nor binary",
+        ))),
+    }
+}
+
+/// Load a program from a file in either form, or from stdin if `path` is
+/// `-`.
+pub fn load_program(path: &str) -> Result<Vec<i64>, LoadError> {
+    if path == "-" {
+        return read_program_from(&mut io::stdin().lock());
+    }
+
+    read_program_from(&mut File::open(path)?)
+}
+
+/// Write a program in a compact binary form, which `read_program_from` and
+/// `load_program` accept as well as text.
+pub fn write_binary<W: Write>(program: &[i64], writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    write_words(writer, program)
+}
+
+pub fn save_binary(program: &[i64], path: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    write_binary(program, &mut writer)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_program_accepts_whitespace_and_comments() {
+        let text = "1, 0,0, ; add\n  3,\r\n99\n; done\n";
+
+        assert_eq!(Ok(vec![1, 0, 0, 3, 99]), parse_program(text));
+    }
+
+    #[test]
+    fn parse_program_accepts_trailing_newline() {
+        assert_eq!(Ok(vec![104, -1, 99]), parse_program("104,-1,99\n"));
+    }
+
+    #[test]
+    fn parse_program_accepts_empty_text() {
+        assert_eq!(Ok(vec![]), parse_program(" \n; nothing here\n"));
+    }
+
+    #[test]
+    fn parse_program_reports_invalid_numbers() {
+        assert_eq!(
+            Err(ParseError::InvalidNumber {
+                line: 2,
+                column: 4,
+                token: "12x".to_string()
+            }),
+            parse_program("1,\n2, 12x,3")
+        );
+    }
+
+    #[test]
+    fn parse_program_reports_missing_numbers() {
+        assert_eq!(
+            Err(ParseError::MissingNumber { line: 1, column: 3 }),
+            parse_program("1,,2")
+        );
+        assert_eq!(
+            Err(ParseError::MissingNumber { line: 1, column: 3 }),
+            parse_program("99,\n")
+        );
+    }
+
+    #[test]
+    fn parse_program_reports_missing_commas() {
+        assert_eq!(
+            Err(ParseError::MissingComma { line: 2, column: 1 }),
+            parse_program("1\n2")
+        );
+    }
+
+    #[test]
+    fn read_program_from_round_trips_binary() {
+        let program = vec![109, -1, 204, 1_000_000_000_000, 99];
+        let mut bytes = Vec::new();
+
+        write_binary(&program, &mut bytes).expect("write failed");
+
+        assert!(bytes.len() < 20);
+        assert_eq!(
+            program,
+            read_program_from(&mut &bytes[..]).expect("read failed")
+        );
+    }
+
+    #[test]
+    fn read_program_from_reads_text() {
+        assert_eq!(
+            vec![3, 0, 99],
+            read_program_from(&mut &b"3,0,99\n"[..]).expect("read failed")
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use super::word::Word;
+use std::collections::HashMap;
+
+const PAGE_SIZE: usize = 1024;
+
+/// Storage for an `Intcomp`'s memory.  Every address exists as far as the
+/// program is concerned; addresses which have never been written read as zero.
+pub trait Memory: Clone {
+    type Word: Word;
+
+    /// Create memory holding `words`, starting at address zero.
+    fn load(words: &[Self::Word]) -> Self;
+
+    fn read(&self, address: usize) -> Self::Word;
+
+    fn write(&mut self, address: usize, value: Self::Word);
+
+    /// One more than the highest address which has been loaded or written.
+    /// This is only a bound: nothing below it need have been allocated.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Raise `len` to at least `len`, as writing a zero just below it would,
+    /// without allocating anything.
+    fn grow_to(&mut self, len: usize);
+
+    /// The address and value of every word which isn't zero, in address
+    /// order.  Costs as much as the memory actually allocated, however far
+    /// apart the words are.
+    fn nonzero(&self) -> Vec<(usize, Self::Word)>;
+
+    /// Copy `other`, word for word and with the same `len`.  Only the words
+    /// which aren't zero are allocated.
+    fn copy_of<N: Memory<Word = Self::Word>>(other: &N) -> Self {
+        let mut memory = Self::load(&[]);
+
+        for (address, word) in other.nonzero() {
+            memory.write(address, word);
+        }
+
+        memory.grow_to(other.len());
+
+        memory
+    }
+
+    /// The words from address zero up to `len`, stopping early at the first
+    /// page of zeros.  This is the program and the data around it, leaving
+    /// out anything it wrote far away.
+    fn image(&self) -> Vec<Self::Word> {
+        let mut end = 0;
+
+        for (address, _) in self.nonzero() {
+            if address >= end + PAGE_SIZE {
+                return (0..end).map(|address| self.read(address)).collect();
+            }
+
+            end = address + 1;
+        }
+
+        if self.len() < end + PAGE_SIZE {
+            end = self.len();
+        }
+
+        (0..end).map(|address| self.read(address)).collect()
+    }
+}
+
+/// A single contiguous block of memory, grown whenever a higher address is
+/// written.  Fast, but writing anything but zero to a very large address
+/// allocates everything below it too.
+#[derive(Clone, Debug, Default)]
+pub struct DenseMemory<W = i64> {
+    words: Vec<W>,
+
+    /// At least `words.len()`; zeros written beyond the words only raise it.
+    len: usize,
+}
+
+impl<W: Word> Memory for DenseMemory<W> {
+    type Word = W;
+
+    fn load(words: &[W]) -> DenseMemory<W> {
+        DenseMemory::from(words.to_vec())
+    }
+
+    fn read(&self, address: usize) -> W {
+        self.words.get(address).cloned().unwrap_or_default()
+    }
+
+    fn write(&mut self, address: usize, value: W) {
+        self.grow_to(address + 1);
+
+        if address >= self.words.len() {
+            // Words beyond the end already read as zero.
+            if value == W::default() {
+                return;
+            }
+
+            self.words.resize(address + 1, W::default());
+        }
+
+        self.words[address] = value;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn grow_to(&mut self, len: usize) {
+        self.len = usize::max(self.len, len);
+    }
+
+    fn nonzero(&self) -> Vec<(usize, W)> {
+        nonzero_words(0, &self.words).collect()
+    }
+}
+
+impl<W> DenseMemory<W> {
+    /// The words allocated, which may stop short of `len`.
+    pub fn into_vec(self) -> Vec<W> {
+        self.words
+    }
+}
+
+impl<W> From<Vec<W>> for DenseMemory<W> {
+    fn from(words: Vec<W>) -> DenseMemory<W> {
+        DenseMemory {
+            len: words.len(),
+            words,
+        }
+    }
+}
+
+/// Zeros allocated or not don't make memory unequal.
+impl<W: Word> PartialEq for DenseMemory<W> {
+    fn eq(&self, other: &DenseMemory<W>) -> bool {
+        self.len == other.len && self.nonzero() == other.nonzero()
+    }
+}
+
+fn nonzero_words<W: Word>(start: usize, words: &[W]) -> impl Iterator<Item = (usize, W)> + '_ {
+    words
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| **word != W::default())
+        .map(move |(offset, word)| (start + offset, *word))
+}
+
+/// Memory allocated in fixed-size pages as they're written, so that programs
+/// which scatter writes across huge addresses only pay for what they touch.
+#[derive(Clone, Debug)]
+pub struct PagedMemory<W = i64> {
+    pages: HashMap<usize, Box<[W; PAGE_SIZE]>>,
+    len: usize,
+}
+
+impl<W> Default for PagedMemory<W> {
+    fn default() -> PagedMemory<W> {
+        PagedMemory {
+            pages: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<W> PagedMemory<W> {
+    /// The number of pages which have been allocated.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl<W: Word> Memory for PagedMemory<W> {
+    type Word = W;
+
+    fn load(words: &[W]) -> PagedMemory<W> {
+        let mut memory = PagedMemory::default();
+
+        for (address, word) in words.iter().enumerate() {
+            memory.write(address, *word);
+        }
+
+        memory.len = words.len();
+
+        memory
+    }
+
+    fn read(&self, address: usize) -> W {
+        match self.pages.get(&(address / PAGE_SIZE)) {
+            Some(page) => page[address % PAGE_SIZE],
+            None => W::default(),
+        }
+    }
+
+    fn write(&mut self, address: usize, value: W) {
+        self.grow_to(address + 1);
+
+        // Unallocated pages already read as zero.
+        if value == W::default() && !self.pages.contains_key(&(address / PAGE_SIZE)) {
+            return;
+        }
+
+        self.pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| Box::new([W::default(); PAGE_SIZE]))[address % PAGE_SIZE] = value;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn grow_to(&mut self, len: usize) {
+        self.len = usize::max(self.len, len);
+    }
+
+    fn nonzero(&self) -> Vec<(usize, W)> {
+        let mut pages = self.pages.keys().cloned().collect::<Vec<_>>();
+
+        pages.sort_unstable();
+
+        pages
+            .iter()
+            .flat_map(|page| nonzero_words(page * PAGE_SIZE, &self.pages[page][..]))
+            .collect()
+    }
+}
+
+/// Pages allocated but holding only zeros don't make memory unequal.
+impl<W: Word> PartialEq for PagedMemory<W> {
+    fn eq(&self, other: &PagedMemory<W>) -> bool {
+        self.len == other.len && self.nonzero() == other.nonzero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise<M: Memory<Word = i64>>() {
+        let mut memory = M::load(&[1, 2, 3]);
+
+        assert_eq!(3, memory.len());
+        assert_eq!(2, memory.read(1));
+        assert_eq!(0, memory.read(5000));
+
+        memory.write(5000, 7);
+
+        assert_eq!(7, memory.read(5000));
+        assert_eq!(5001, memory.len());
+        assert_eq!(vec![(0, 1), (1, 2), (2, 3), (5000, 7)], memory.nonzero());
+        assert_eq!(vec![1, 2, 3], memory.image());
+
+        let copy = PagedMemory::copy_of(&memory);
+
+        assert_eq!(memory.len(), copy.len());
+        assert_eq!(memory.nonzero(), copy.nonzero());
+    }
+
+    #[test]
+    fn dense_memory_works() {
+        exercise::<DenseMemory>();
+    }
+
+    #[test]
+    fn paged_memory_works() {
+        exercise::<PagedMemory>();
+    }
+
+    #[test]
+    fn paged_memory_allocates_only_touched_pages() {
+        let mut memory = PagedMemory::<i64>::load(&[1, 2, 3]);
+
+        memory.write(1_000_000_000_000, 5);
+        memory.write(2_000_000_000_000, 0);
+
+        assert_eq!(2, memory.page_count());
+        assert_eq!(5, memory.read(1_000_000_000_000));
+        assert_eq!(2_000_000_000_001, memory.len());
+        assert_eq!(
+            vec![(0, 1), (1, 2), (2, 3), (1_000_000_000_000, 5)],
+            memory.nonzero()
+        );
+        assert_eq!(vec![1, 2, 3], memory.image());
+    }
+
+    #[test]
+    fn dense_memory_allocates_only_nonzero_writes() {
+        let mut memory = PagedMemory::<i64>::load(&[1, 2, 3]);
+
+        memory.write(1_000_000_000_000, 0);
+
+        let dense = DenseMemory::copy_of(&memory);
+
+        assert_eq!(1_000_000_000_001, dense.len());
+        assert_eq!(3, dense.clone().into_vec().len());
+        assert_eq!(memory, PagedMemory::copy_of(&dense));
+    }
+}
@@ -0,0 +1,435 @@
+use super::{Intcomp, IntcompError, RunState};
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Packet {
+    pub destination: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Why `Network::run` returned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetworkState {
+    /// The monitor asked the network to stop.
+    Stopped,
+
+    /// Every machine went idle and the monitor didn't wake any of them.
+    Idle,
+
+    /// Every machine halted.
+    Halted,
+}
+
+/// Watches a network from outside, receiving the packets which aren't
+/// addressed to any machine and deciding what to do when it goes idle.
+pub trait Monitor {
+    /// Called with each packet sent to an address with no machine, or to a
+    /// machine which has halted.  Returning `false` stops the network.
+    fn receive(&mut self, packet: Packet) -> bool;
+
+    /// Called when every machine has been idle for long enough.  Returning a
+    /// packet delivers it; `None` lets the network stop.
+    fn idle(&mut self) -> Option<Packet>;
+}
+
+/// A monitor which stores the last packet sent to its address and, whenever
+/// the network goes idle, resends it to the machine at `wake`.  It stops the
+/// network if it would send the same `y` twice in a row.
+pub struct Nat {
+    address: i64,
+    wake: i64,
+    last: Option<Packet>,
+
+    /// Every packet received, in order.
+    pub received: Vec<Packet>,
+
+    /// Every packet sent to wake the network, in order.
+    pub sent: Vec<Packet>,
+}
+
+impl Nat {
+    pub fn new(address: i64, wake: i64) -> Nat {
+        Nat {
+            address,
+            wake,
+            last: None,
+            received: Vec::new(),
+            sent: Vec::new(),
+        }
+    }
+}
+
+impl Monitor for Nat {
+    fn receive(&mut self, packet: Packet) -> bool {
+        self.received.push(packet);
+
+        if packet.destination == self.address {
+            self.last = Some(packet);
+        }
+
+        true
+    }
+
+    fn idle(&mut self) -> Option<Packet> {
+        let packet = Packet {
+            destination: self.wake,
+            ..self.last?
+        };
+
+        if self.sent.last().map(|sent| sent.y) == Some(packet.y) {
+            return None;
+        }
+
+        self.sent.push(packet);
+
+        Some(packet)
+    }
+}
+
+/// Machines running the same program, each booted with its own address and
+/// sending packets as triples of outputs.  Machines take turns in address
+/// order, so a network always runs the same way.
+pub struct Network {
+    machines: Vec<Intcomp>,
+    queues: Vec<VecDeque<Packet>>,
+
+    /// Outputs which don't yet make up a whole packet.
+    partial: Vec<Vec<i64>>,
+
+    halted: Vec<bool>,
+    idle_threshold: NonZeroUsize,
+}
+
+impl Network {
+    /// Boot `size` machines with addresses counting up from zero.
+    pub fn new(program: &[i64], size: usize) -> Network {
+        let machines = (0..size)
+            .map(|address| {
+                let mut intcomp = Intcomp::new(program);
+
+                intcomp.send_input(address as i64);
+
+                intcomp
+            })
+            .collect();
+
+        Network {
+            machines,
+            queues: vec![VecDeque::new(); size],
+            partial: vec![Vec::new(); size],
+            halted: vec![false; size],
+            idle_threshold: NonZeroUsize::new(2).unwrap(),
+        }
+    }
+
+    /// Set how many rounds every machine must spend idle before the network
+    /// counts as idle.  A machine is idle for a round when it has no packets
+    /// waiting and sends none, so even one round only counts once every
+    /// machine has had a turn.  Defaults to 2.
+    pub fn set_idle_threshold(&mut self, rounds: NonZeroUsize) {
+        self.idle_threshold = rounds;
+    }
+
+    /// Deliver `packet` as if it had come from outside the network, returning
+    /// it if there's no machine at its destination or that machine has halted.
+    pub fn send(&mut self, packet: Packet) -> Option<Packet> {
+        let address = packet.destination as usize;
+
+        match self.queues.get_mut(address) {
+            Some(queue) if packet.destination >= 0 && !self.halted[address] => {
+                queue.push_back(packet);
+
+                None
+            }
+
+            _ => Some(packet),
+        }
+    }
+
+    /// Run until the monitor stops the network, it goes idle for good, or
+    /// every machine halts.
+    pub fn run<M: Monitor>(&mut self, monitor: &mut M) -> Result<NetworkState, IntcompError> {
+        let mut idle_rounds = 0;
+
+        loop {
+            if self.halted.iter().all(|halted| *halted) {
+                return Ok(NetworkState::Halted);
+            }
+
+            let mut idle = true;
+
+            for address in 0..self.machines.len() {
+                let waiting = !self.queues[address].is_empty();
+                let sent = self.run_machine(address)?;
+
+                idle &= !waiting && sent.is_empty();
+
+                for packet in sent {
+                    if let Some(packet) = self.send(packet) {
+                        if !monitor.receive(packet) {
+                            return Ok(NetworkState::Stopped);
+                        }
+                    }
+                }
+            }
+
+            // Packets sent to machines which already had their turn this
+            // round mean the network isn't idle.
+            if idle
+                && self
+                    .queues
+                    .iter()
+                    .zip(&self.halted)
+                    .all(|(queue, halted)| *halted || queue.is_empty())
+            {
+                idle_rounds += 1;
+            } else {
+                idle_rounds = 0;
+            }
+
+            if idle_rounds >= self.idle_threshold.get() {
+                match monitor.idle() {
+                    Some(packet) => {
+                        if let Some(packet) = self.send(packet) {
+                            if !monitor.receive(packet) {
+                                return Ok(NetworkState::Stopped);
+                            }
+                        }
+                    }
+
+                    None => return Ok(NetworkState::Idle),
+                }
+
+                idle_rounds = 0;
+            }
+        }
+    }
+
+    /// Give the machine at `address` its waiting packets, or `-1` if there
+    /// are none, and run it until it wants more input.  Returns the packets
+    /// it sent.  Packets waiting for a halted machine are dropped.
+    fn run_machine(&mut self, address: usize) -> Result<Vec<Packet>, IntcompError> {
+        if self.halted[address] {
+            self.queues[address].clear();
+
+            return Ok(Vec::new());
+        }
+
+        let intcomp = &mut self.machines[address];
+
+        if self.queues[address].is_empty() {
+            intcomp.send_input(-1);
+        }
+
+        for packet in self.queues[address].drain(..) {
+            intcomp.send_input(packet.x);
+            intcomp.send_input(packet.y);
+        }
+
+        self.halted[address] = intcomp.execute()? == RunState::Halted;
+
+        let partial = &mut self.partial[address];
+
+        while let Some(output) = intcomp.receive_output() {
+            partial.push(output);
+        }
+
+        let whole = partial.len() - partial.len() % 3;
+
+        Ok(partial
+            .drain(..whole)
+            .collect::<Vec<_>>()
+            .chunks(3)
+            .map(|chunk| Packet {
+                destination: chunk[0],
+                x: chunk[1],
+                y: chunk[2],
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::assembler::assemble;
+
+    /// Passes each packet it receives on to the next address, adding
+    /// `increment` to its `y`.
+    fn relay(increment: i64) -> Vec<i64> {
+        assemble(&format!(
+            "
+                    IN [address]
+                    ADD [address], #1 -> [next]
+            loop:   IN [x]
+                    EQ [x], #-1 -> [empty]
+                    JT [empty], #loop
+                    IN [y]
+                    ADD [y], #{} -> [y]
+                    OUT [next]
+                    OUT [x]
+                    OUT [y]
+                    JT #1, #loop
+            address: .data 0
+            next:   .data 0
+            x:      .data 0
+            y:      .data 0
+            empty:  .data 0
+            ",
+            increment
+        ))
+        .expect("assembly failed")
+    }
+
+    struct Recorder(Vec<Packet>);
+
+    impl Monitor for Recorder {
+        fn receive(&mut self, packet: Packet) -> bool {
+            self.0.push(packet);
+
+            true
+        }
+
+        fn idle(&mut self) -> Option<Packet> {
+            None
+        }
+    }
+
+    #[test]
+    fn network_routes_packets() {
+        let mut network = Network::new(&relay(1), 3);
+        let mut recorder = Recorder(Vec::new());
+
+        network.send(Packet {
+            destination: 0,
+            x: 7,
+            y: 0,
+        });
+
+        assert_eq!(Ok(NetworkState::Idle), network.run(&mut recorder));
+        assert_eq!(
+            vec![Packet {
+                destination: 3,
+                x: 7,
+                y: 3,
+            }],
+            recorder.0
+        );
+    }
+
+    #[test]
+    fn network_waits_a_whole_round_at_lowest_threshold() {
+        let mut network = Network::new(&relay(1), 3);
+        let mut recorder = Recorder(Vec::new());
+
+        network.set_idle_threshold(NonZeroUsize::new(1).unwrap());
+        network.send(Packet {
+            destination: 0,
+            x: 7,
+            y: 0,
+        });
+
+        assert_eq!(Ok(NetworkState::Idle), network.run(&mut recorder));
+        assert_eq!(
+            vec![Packet {
+                destination: 3,
+                x: 7,
+                y: 3,
+            }],
+            recorder.0
+        );
+    }
+
+    #[test]
+    fn network_stops_when_monitor_asks() {
+        struct StopAtOnce;
+
+        impl Monitor for StopAtOnce {
+            fn receive(&mut self, _packet: Packet) -> bool {
+                false
+            }
+
+            fn idle(&mut self) -> Option<Packet> {
+                panic!("network went idle");
+            }
+        }
+
+        let mut network = Network::new(&relay(0), 1);
+
+        network.send(Packet {
+            destination: 0,
+            x: 1,
+            y: 2,
+        });
+
+        assert_eq!(Ok(NetworkState::Stopped), network.run(&mut StopAtOnce));
+    }
+
+    #[test]
+    fn nat_wakes_network_until_y_repeats() {
+        let mut network = Network::new(&relay(0), 2);
+        let mut nat = Nat::new(2, 0);
+
+        network.send(Packet {
+            destination: 0,
+            x: 4,
+            y: 5,
+        });
+
+        assert_eq!(Ok(NetworkState::Idle), network.run(&mut nat));
+        assert_eq!(
+            vec![Packet {
+                destination: 0,
+                x: 4,
+                y: 5,
+            }],
+            nat.sent
+        );
+        assert_eq!(2, nat.received.len());
+    }
+
+    #[test]
+    fn network_reports_packets_for_halted_machines() {
+        // Address 0 halts at once; any other address sends it a packet.
+        let program = assemble(
+            "
+                    IN [address]
+                    JF [address], #halt
+                    OUT #0
+                    OUT #10
+                    OUT #20
+            loop:   IN [x]
+                    JT #1, #loop
+            halt:   HLT
+            address: .data 0
+            x:      .data 0
+            ",
+        )
+        .expect("assembly failed");
+
+        let mut network = Network::new(&program, 2);
+        let mut recorder = Recorder(Vec::new());
+
+        assert_eq!(Ok(NetworkState::Idle), network.run(&mut recorder));
+        assert_eq!(
+            vec![Packet {
+                destination: 0,
+                x: 10,
+                y: 20,
+            }],
+            recorder.0
+        );
+    }
+
+    #[test]
+    fn network_reports_halt() {
+        let mut network = Network::new(&[3, 0, 99], 2);
+
+        assert_eq!(
+            Ok(NetworkState::Halted),
+            network.run(&mut Recorder(Vec::new()))
+        );
+    }
+}
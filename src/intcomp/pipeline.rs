@@ -0,0 +1,302 @@
+use super::io::IntcodeIo;
+use super::{Intcomp, IntcompError, RunState};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+/// What the machines in a pipeline are doing, shared between their threads so
+/// that they can tell when none of them can make progress.
+struct Counts {
+    /// Machines which haven't stopped yet.
+    live: usize,
+
+    /// Live machines which are blocked waiting for input.
+    idle: usize,
+
+    /// Values sent to live machines which they haven't received yet.
+    in_flight: usize,
+
+    stopped: Vec<bool>,
+    deadlocked: bool,
+}
+
+#[derive(Clone)]
+struct Monitor(Arc<(Mutex<Counts>, Condvar)>);
+
+impl Monitor {
+    fn lock(&self) -> MutexGuard<'_, Counts> {
+        (self.0).0.lock().expect("pipeline monitor poisoned")
+    }
+
+    fn notify(&self) {
+        (self.0).1.notify_all();
+    }
+
+    fn wait<'a>(&self, counts: MutexGuard<'a, Counts>) -> MutexGuard<'a, Counts> {
+        (self.0).1.wait(counts).expect("pipeline monitor poisoned")
+    }
+}
+
+impl Counts {
+    /// Every live machine is waiting for input which nobody is going to send.
+    fn is_deadlocked(&self) -> bool {
+        self.live > 0 && self.idle == self.live && self.in_flight == 0
+    }
+}
+
+/// One machine's connections to the rest of the pipeline.
+struct Wire {
+    id: usize,
+    input: Receiver<i64>,
+    outputs: Vec<(usize, Sender<i64>)>,
+    sent: Vec<i64>,
+    monitor: Monitor,
+}
+
+impl Wire {
+    /// Leave the pipeline, discarding anything which was sent to this
+    /// machine but never read.
+    fn disconnect(&self) {
+        let mut counts = self.monitor.lock();
+
+        counts.live -= 1;
+        counts.stopped[self.id] = true;
+        counts.in_flight -= self.input.try_iter().count();
+
+        if counts.is_deadlocked() {
+            counts.deadlocked = true;
+        }
+
+        self.monitor.notify();
+    }
+}
+
+impl IntcodeIo for Wire {
+    fn input(&mut self) -> Option<i64> {
+        let mut counts = self.monitor.lock();
+
+        loop {
+            if let Ok(value) = self.input.try_recv() {
+                counts.in_flight -= 1;
+
+                return Some(value);
+            }
+
+            if counts.deadlocked {
+                return None;
+            }
+
+            counts.idle += 1;
+
+            if counts.is_deadlocked() {
+                counts.idle -= 1;
+                counts.deadlocked = true;
+                self.monitor.notify();
+
+                return None;
+            }
+
+            counts = self.monitor.wait(counts);
+            counts.idle -= 1;
+        }
+    }
+
+    fn output(&mut self, value: i64) {
+        let mut counts = self.monitor.lock();
+
+        self.sent.push(value);
+
+        for (destination, sender) in &self.outputs {
+            if !counts.stopped[*destination] {
+                counts.in_flight += 1;
+
+                // The receiver lives as long as its machine is running.
+                let _ = sender.send(value);
+            }
+        }
+
+        self.monitor.notify();
+    }
+}
+
+/// How a machine in a pipeline finished.
+pub struct Finished {
+    pub intcomp: Intcomp,
+
+    /// `AwaitingInput` means the machine was deadlocked.
+    pub state: Result<RunState, IntcompError>,
+
+    /// Every value the machine output, whether or not it was connected to
+    /// another machine.
+    pub outputs: Vec<i64>,
+}
+
+/// Several machines, each run on its own thread, with the outputs of each
+/// connected to the inputs of others in any arrangement.
+#[derive(Default)]
+pub struct Pipeline {
+    machines: Vec<Intcomp>,
+    connections: Vec<(usize, usize)>,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Add a machine, returning the id used to connect it.
+    pub fn add(&mut self, intcomp: Intcomp) -> usize {
+        self.machines.push(intcomp);
+        self.machines.len() - 1
+    }
+
+    /// Send every output of machine `from` to machine `to`.  A machine
+    /// connected to several others sends each of them a copy.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.connections.push((from, to));
+    }
+
+    /// Queue an input for machine `id` before the pipeline starts.
+    pub fn send_input(&mut self, id: usize, input: i64) {
+        self.machines[id].send_input(input);
+    }
+
+    /// Run every machine until it halts, fails, or is deadlocked because every
+    /// machine still running is waiting for input.
+    pub fn run(self) -> Vec<Finished> {
+        let Pipeline {
+            machines,
+            connections,
+        } = self;
+
+        let monitor = Monitor(Arc::new((
+            Mutex::new(Counts {
+                live: machines.len(),
+                idle: 0,
+                in_flight: 0,
+                stopped: vec![false; machines.len()],
+                deadlocked: false,
+            }),
+            Condvar::new(),
+        )));
+
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            machines.iter().map(|_| mpsc::channel()).unzip();
+
+        let handles = machines
+            .into_iter()
+            .zip(receivers)
+            .enumerate()
+            .map(|(id, (mut intcomp, input))| {
+                let mut wire = Wire {
+                    id,
+                    input,
+                    outputs: connections
+                        .iter()
+                        .filter(|(from, _)| *from == id)
+                        .map(|(_, to)| (*to, senders[*to].clone()))
+                        .collect(),
+                    sent: Vec::new(),
+                    monitor: monitor.clone(),
+                };
+
+                thread::spawn(move || {
+                    let state = intcomp.execute_io(&mut wire);
+
+                    wire.disconnect();
+
+                    Finished {
+                        intcomp,
+                        state,
+                        outputs: wire.sent,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("pipeline thread panicked"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::fixtures::DOUBLER;
+
+    #[test]
+    fn pipeline_runs_chain() {
+        let mut pipeline = Pipeline::new();
+        let first = pipeline.add(Intcomp::new(&DOUBLER));
+        let second = pipeline.add(Intcomp::new(&DOUBLER));
+
+        pipeline.connect(first, second);
+        pipeline.send_input(first, 1);
+        pipeline.send_input(first, 5);
+
+        let finished = pipeline.run();
+
+        assert_eq!(vec![2, 10], finished[first].outputs);
+        assert_eq!(vec![4, 20], finished[second].outputs);
+
+        // Nothing ever sends either machine the zero which would halt it.
+        assert!(finished
+            .iter()
+            .all(|machine| machine.state == Ok(RunState::AwaitingInput)));
+    }
+
+    #[test]
+    fn pipeline_runs_fan_out() {
+        let mut pipeline = Pipeline::new();
+        let source = pipeline.add(Intcomp::new(&[104, 1, 104, 0, 99]));
+        let left = pipeline.add(Intcomp::new(&DOUBLER));
+        let right = pipeline.add(Intcomp::new(&DOUBLER));
+
+        pipeline.connect(source, left);
+        pipeline.connect(source, right);
+
+        let finished = pipeline.run();
+
+        for machine in finished {
+            assert_eq!(Ok(RunState::Halted), machine.state);
+        }
+    }
+
+    #[test]
+    fn pipeline_detects_deadlock_in_loop() {
+        let mut pipeline = Pipeline::new();
+        let first = pipeline.add(Intcomp::new(&DOUBLER));
+        let second = pipeline.add(Intcomp::new(&DOUBLER));
+
+        pipeline.connect(first, second);
+        pipeline.connect(second, first);
+
+        let finished = pipeline.run();
+
+        assert_eq!(Ok(RunState::AwaitingInput), finished[first].state);
+        assert_eq!(Ok(RunState::AwaitingInput), finished[second].state);
+    }
+
+    #[test]
+    fn pipeline_reports_errors() {
+        let mut pipeline = Pipeline::new();
+        let broken = pipeline.add(Intcomp::new(&[104, 1, 0]));
+        let waiting = pipeline.add(Intcomp::new(&DOUBLER));
+
+        pipeline.connect(broken, waiting);
+
+        let finished = pipeline.run();
+
+        assert_eq!(
+            Err(IntcompError::InvalidOpcode {
+                address: 2,
+                opcode: 0
+            }),
+            finished[broken].state
+        );
+        assert_eq!(vec![2], finished[waiting].outputs);
+        assert_eq!(Ok(RunState::AwaitingInput), finished[waiting].state);
+    }
+}
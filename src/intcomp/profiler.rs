@@ -0,0 +1,224 @@
+use super::disassembler::{disassemble, DisassembledInstruction};
+use super::memory::Memory;
+use super::{Hook, Intcomp};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// A `Hook` which counts how often each instruction runs and each memory
+/// cell is read and written.  Profiling is opt-in: pass a `Profiler` to
+/// `Intcomp::execute_with` to use it.
+pub struct Profiler {
+    program: Vec<i64>,
+    executions: BTreeMap<usize, u64>,
+    opcodes: BTreeMap<i64, u64>,
+    reads: BTreeMap<usize, u64>,
+    writes: BTreeMap<usize, u64>,
+}
+
+impl Profiler {
+    /// Start profiling from `intcomp`'s current state, whose memory image is
+    /// used as the program when reporting coverage.
+    pub fn new<M: Memory<Word = i64>>(intcomp: &Intcomp<M>) -> Profiler {
+        Profiler {
+            program: intcomp.memory.image(),
+            executions: BTreeMap::new(),
+            opcodes: BTreeMap::new(),
+            reads: BTreeMap::new(),
+            writes: BTreeMap::new(),
+        }
+    }
+
+    pub fn executions(&self, address: usize) -> u64 {
+        self.executions.get(&address).cloned().unwrap_or(0)
+    }
+
+    /// The number of times each opcode was executed.
+    pub fn opcodes(&self) -> &BTreeMap<i64, u64> {
+        &self.opcodes
+    }
+
+    pub fn reads(&self, address: usize) -> u64 {
+        self.reads.get(&address).cloned().unwrap_or(0)
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.writes.get(&address).cloned().unwrap_or(0)
+    }
+
+    /// The addresses of instructions in the program which never ran.  Words
+    /// which don't decode as instructions are assumed to be data.
+    pub fn unexecuted(&self) -> Vec<usize> {
+        disassemble(&self.program)
+            .iter()
+            .filter(|line| matches!(line, DisassembledInstruction::Instruction { .. }))
+            .map(DisassembledInstruction::address)
+            .filter(|address| self.executions(*address) == 0)
+            .collect()
+    }
+
+    /// The program's disassembly with the counts for each line alongside.
+    /// Reads and writes are totalled over every word on the line.
+    pub fn table(&self) -> String {
+        let mut table = format!("{:>10} {:>10} {:>10}  listing\n", "runs", "reads", "writes");
+        let mut instructions = 0;
+        let mut executed = 0;
+
+        for line in disassemble(&self.program) {
+            let words = line.address()..line.address() + line.word_count();
+            let reads = words
+                .clone()
+                .map(|address| self.reads(address))
+                .sum::<u64>();
+            let writes = words.map(|address| self.writes(address)).sum::<u64>();
+
+            let runs = match line {
+                DisassembledInstruction::Instruction { address, .. } => {
+                    instructions += 1;
+
+                    match self.executions(address) {
+                        0 => "never".to_string(),
+
+                        runs => {
+                            executed += 1;
+                            runs.to_string()
+                        }
+                    }
+                }
+
+                DisassembledInstruction::Data { .. } => String::new(),
+            };
+
+            writeln!(table, "{:>10} {:>10} {:>10}  {}", runs, reads, writes, line)
+                .expect("writing to a string failed");
+        }
+
+        writeln!(table, "\nopcode       runs").expect("writing to a string failed");
+
+        for (opcode, runs) in &self.opcodes {
+            writeln!(table, "{:>6} {:>10}", opcode, runs).expect("writing to a string failed");
+        }
+
+        writeln!(
+            table,
+            "\n{} of {} instructions executed.",
+            executed, instructions
+        )
+        .expect("writing to a string failed");
+
+        table
+    }
+
+    /// The same information as `table`, as JSON.  Only addresses which were
+    /// executed, read or written are listed.
+    pub fn to_json(&self) -> String {
+        let mut addresses = self
+            .executions
+            .keys()
+            .chain(self.reads.keys())
+            .chain(self.writes.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        let addresses = addresses
+            .iter()
+            .map(|address| {
+                format!(
+                    "{{\"address\":{},\"executions\":{},\"reads\":{},\"writes\":{}}}",
+                    address,
+                    self.executions(*address),
+                    self.reads(*address),
+                    self.writes(*address)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let opcodes = self
+            .opcodes
+            .iter()
+            .map(|(opcode, runs)| format!("\"{}\":{}", opcode, runs))
+            .collect::<Vec<_>>();
+
+        let unexecuted = self
+            .unexecuted()
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"addresses\":[{}],\"opcodes\":{{{}}},\"unexecuted\":[{}]}}",
+            addresses.join(","),
+            opcodes.join(","),
+            unexecuted.join(",")
+        )
+    }
+}
+
+impl Hook for Profiler {
+    fn after_read(&mut self, address: usize, _value: i64) {
+        *self.reads.entry(address).or_insert(0) += 1;
+    }
+
+    fn before_write(&mut self, address: usize, _value: i64) -> bool {
+        *self.writes.entry(address).or_insert(0) += 1;
+
+        true
+    }
+
+    fn after_instruction(&mut self, ip: usize, instruction: i64) {
+        *self.executions.entry(ip).or_insert(0) += 1;
+        *self.opcodes.entry(instruction % 100).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::fixtures::COUNTDOWN;
+
+    fn profile() -> Profiler {
+        let mut intcomp = Intcomp::new(&COUNTDOWN);
+        let mut profiler = Profiler::new(&intcomp);
+
+        intcomp
+            .execute_with(&mut profiler)
+            .expect("execution failed");
+
+        profiler
+    }
+
+    #[test]
+    fn profiler_counts_executions() {
+        let profiler = profile();
+
+        assert_eq!(3, profiler.executions(0));
+        assert_eq!(3, profiler.executions(6));
+        assert_eq!(1, profiler.executions(9));
+        assert_eq!(1, profiler.executions(14));
+        assert_eq!(Some(&3), profiler.opcodes().get(&1));
+    }
+
+    #[test]
+    fn profiler_counts_memory_access() {
+        let profiler = profile();
+
+        // Output, add and jump each read [15] once per loop.
+        assert_eq!(9, profiler.reads(15));
+        assert_eq!(3, profiler.writes(15));
+    }
+
+    #[test]
+    fn profiler_finds_unexecuted_code() {
+        assert_eq!(vec![12], profile().unexecuted());
+    }
+
+    #[test]
+    fn profiler_reports_as_json() {
+        let json = profile().to_json();
+
+        assert!(json.starts_with("{\"addresses\":[{\"address\":0,\"executions\":3,"));
+        assert!(json.ends_with("\"unexecuted\":[12]}"));
+    }
+}
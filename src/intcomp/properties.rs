@@ -0,0 +1,333 @@
+//! Property tests which run randomly generated programs, comparing `Intcomp`
+//! with a reference interpreter and with itself.
+
+use super::assembler::assemble;
+use super::compiler::Compiled;
+use super::disassembler::disassemble;
+use super::memory::{Memory, PagedMemory};
+use super::{Instruction, Intcomp, IntcompError, RunState};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::collections::VecDeque;
+
+const MEMORY_LIMIT: usize = 4096;
+const STEP_LIMIT: usize = 1000;
+
+/// Opcode, number of operands read and whether a target is written.
+const OPCODES: [(i64, usize, bool); 10] = [
+    (1, 2, true),
+    (2, 2, true),
+    (3, 0, true),
+    (4, 1, false),
+    (5, 2, false),
+    (6, 2, false),
+    (7, 2, true),
+    (8, 2, true),
+    (9, 1, false),
+    (99, 0, false),
+];
+
+/// A single valid instruction with small operands, so that most of them
+/// refer to addresses within the program.
+fn instruction() -> impl Strategy<Value = Vec<i64>> {
+    (0..OPCODES.len(), vec(0..3_i64, 3), vec(-5..40_i64, 3)).prop_map(
+        |(index, mut modes, operands)| {
+            let (opcode, reads, writes) = OPCODES[index];
+            let count = reads + writes as usize;
+
+            if writes && modes[reads] == 1 {
+                modes[reads] = 0;
+            }
+
+            let word = modes[..count]
+                .iter()
+                .enumerate()
+                .fold(opcode, |word, (index, mode)| {
+                    word + mode * 10_i64.pow(index as u32 + 2)
+                });
+
+            Some(word)
+                .into_iter()
+                .chain(operands[..count].to_vec())
+                .collect()
+        },
+    )
+}
+
+/// Either a sequence of valid instructions followed by some data, or any
+/// words at all.
+fn program() -> impl Strategy<Value = Vec<i64>> {
+    prop_oneof![
+        3 => (vec(instruction(), 1..12), vec(-3..30_i64, 0..16)).prop_map(
+            |(instructions, data)| instructions.into_iter().flatten().chain(data).collect()
+        ),
+        1 => vec(any::<i64>(), 0..32),
+    ]
+}
+
+fn limited(program: &[i64]) -> Intcomp {
+    let mut intcomp = Intcomp::new(program);
+
+    intcomp.set_memory_limit(Some(MEMORY_LIMIT));
+
+    intcomp
+}
+
+/// A deliberately plain interpreter written from the puzzle descriptions,
+/// sharing no code with `Intcomp`, with the same memory limit.
+struct Reference {
+    memory: Vec<i64>,
+    ip: usize,
+    bp: i64,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+}
+
+impl Reference {
+    fn new(program: &[i64], input: &[i64]) -> Reference {
+        Reference {
+            memory: program.to_vec(),
+            ip: 0,
+            bp: 0,
+            input: input.iter().cloned().collect(),
+            output: Vec::new(),
+        }
+    }
+
+    fn run(&mut self, limit: usize) -> Result<RunState, IntcompError> {
+        for _ in 0..limit {
+            if let Some(state) = self.step()? {
+                return Ok(state);
+            }
+        }
+
+        Ok(RunState::StepLimitReached)
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).cloned().unwrap_or(0)
+    }
+
+    fn check(&self, target: i64) -> Result<usize, IntcompError> {
+        let address = self.ip;
+
+        if target < 0 {
+            Err(IntcompError::NegativeAddress { address, target })
+        } else if target as usize >= MEMORY_LIMIT {
+            Err(IntcompError::AddressLimitExceeded { address, target })
+        } else {
+            Ok(target as usize)
+        }
+    }
+
+    /// The address operand `n` (counting from zero) refers to.
+    fn address(&self, modes: &[i64], n: usize) -> Result<usize, IntcompError> {
+        let operand = self.read(self.ip + 1 + n);
+
+        match modes[n] {
+            0 => self.check(operand),
+            1 => Ok(self.ip + 1 + n),
+
+            _ => match self.bp.checked_add(operand) {
+                Some(target) => self.check(target),
+                None => Err(IntcompError::Overflow { address: self.ip }),
+            },
+        }
+    }
+
+    fn value(&self, modes: &[i64], n: usize) -> Result<i64, IntcompError> {
+        Ok(self.read(self.address(modes, n)?))
+    }
+
+    fn target(&self, modes: &[i64], n: usize) -> Result<usize, IntcompError> {
+        if modes[n] == 1 {
+            return Err(IntcompError::ImmediateWrite { address: self.ip });
+        }
+
+        self.address(modes, n)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+
+        self.memory[address] = value;
+    }
+
+    fn step(&mut self) -> Result<Option<RunState>, IntcompError> {
+        let address = self.ip;
+
+        if address >= self.memory.len() {
+            return Err(IntcompError::IpOutOfBounds { address });
+        }
+
+        let word = self.memory[address];
+
+        let operands = match word % 100 {
+            1 | 2 | 7 | 8 => 3,
+            5 | 6 => 2,
+            3 | 4 | 9 => 1,
+            99 => 0,
+            _ => {
+                return Err(IntcompError::InvalidOpcode {
+                    address,
+                    opcode: word,
+                })
+            }
+        };
+
+        let mut modes = Vec::new();
+
+        for n in 0..operands {
+            let digit = (word / 10_i64.pow(n + 2)) % 10;
+
+            if !(0..=2).contains(&digit) {
+                return Err(IntcompError::InvalidMode {
+                    address,
+                    digit: digit as u8,
+                });
+            }
+
+            modes.push(digit);
+        }
+
+        let overflow = IntcompError::Overflow { address };
+
+        match word % 100 {
+            opcode @ 1 | opcode @ 2 | opcode @ 7 | opcode @ 8 => {
+                let left = self.value(&modes, 0)?;
+                let right = self.value(&modes, 1)?;
+                let target = self.target(&modes, 2)?;
+
+                let result = match opcode {
+                    1 => left.checked_add(right).ok_or(overflow)?,
+                    2 => left.checked_mul(right).ok_or(overflow)?,
+                    7 => (left < right) as i64,
+                    _ => (left == right) as i64,
+                };
+
+                self.write(target, result);
+                self.ip += 4;
+            }
+
+            3 => {
+                let target = self.target(&modes, 0)?;
+
+                match self.input.pop_front() {
+                    Some(input) => self.write(target, input),
+                    None => return Ok(Some(RunState::AwaitingInput)),
+                }
+
+                self.ip += 2;
+            }
+
+            4 => {
+                let value = self.value(&modes, 0)?;
+
+                self.output.push(value);
+                self.ip += 2;
+            }
+
+            opcode @ 5 | opcode @ 6 => {
+                let condition = self.value(&modes, 0)?;
+                let target = self.value(&modes, 1)?;
+
+                if (condition != 0) == (opcode == 5) {
+                    self.ip = self.check(target)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+
+            9 => {
+                let offset = self.value(&modes, 0)?;
+
+                self.bp = self.bp.checked_add(offset).ok_or(overflow)?;
+                self.ip += 2;
+            }
+
+            _ => return Ok(Some(RunState::Halted)),
+        }
+
+        Ok(None)
+    }
+}
+
+proptest! {
+    #[test]
+    fn decoding_agrees_with_assembler(instruction in instruction()) {
+        let line = disassemble(&instruction)[0].to_string();
+
+        prop_assert_eq!(&instruction, &assemble(&line).expect("assembly failed"));
+        prop_assert_eq!(
+            Instruction::parse(instruction[0], 0),
+            Instruction::decode(instruction[0], 0)
+        );
+    }
+
+    #[test]
+    fn intcomp_matches_reference(program in program(), input in vec(-10..10_i64, 0..4)) {
+        let mut intcomp = limited(&program);
+        let mut reference = Reference::new(&program, &input);
+
+        for value in &input {
+            intcomp.send_input(*value);
+        }
+
+        prop_assert_eq!(reference.run(STEP_LIMIT), intcomp.run_for(STEP_LIMIT));
+
+        let snapshot = intcomp.snapshot();
+
+        prop_assert_eq!(reference.output, snapshot.output);
+        prop_assert_eq!(PagedMemory::load(&reference.memory), snapshot.memory);
+        prop_assert_eq!(reference.ip, snapshot.ip);
+        prop_assert_eq!(reference.bp, snapshot.bp);
+    }
+
+    #[test]
+    fn compiled_matches_interpreter(program in program(), input in vec(-10..10_i64, 0..4)) {
+        let mut interpreted = limited(&program);
+        let mut compiled = limited(&program);
+
+        interpreted.set_instruction_limit(Some(STEP_LIMIT as u64));
+
+        for value in input {
+            interpreted.send_input(value);
+            compiled.send_input(value);
+        }
+
+        let expected = interpreted.execute();
+
+        // The compiled code doesn't take an instruction limit, so only
+        // programs which finish within it can be compared.
+        if !matches!(expected, Err(IntcompError::InstructionLimitExceeded { .. })) {
+            prop_assert_eq!(expected, Compiled::new(&program).execute(&mut compiled));
+            prop_assert_eq!(interpreted.snapshot(), compiled.snapshot());
+        }
+    }
+
+    #[test]
+    fn snapshot_restore_repeats_run(
+        program in program(),
+        input in vec(-10..10_i64, 0..4),
+        split in 0..50_usize,
+    ) {
+        let mut intcomp = limited(&program);
+
+        for value in input {
+            intcomp.send_input(value);
+        }
+
+        let _ = intcomp.run_for(split);
+
+        let snapshot = intcomp.snapshot();
+        let first = intcomp.run_for(STEP_LIMIT);
+        let after_first = intcomp.snapshot();
+
+        intcomp.restore(&snapshot);
+
+        prop_assert_eq!(first, intcomp.run_for(STEP_LIMIT));
+        prop_assert_eq!(after_first, intcomp.snapshot());
+    }
+}
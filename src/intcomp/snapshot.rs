@@ -0,0 +1,213 @@
+use super::memory::{Memory, PagedMemory};
+use super::varint::{
+    read_memory, read_signed, read_unsigned, read_words, write_memory, write_signed,
+    write_unsigned, write_words,
+};
+use super::Intcomp;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+const MAGIC: &[u8; 4] = b"ICS2";
+
+/// A copy of everything which makes up a machine's state, which can be
+/// restored later or saved to disk.  Memory is kept sparse, so a snapshot
+/// costs only as much as the words the machine actually holds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub memory: PagedMemory,
+    pub ip: usize,
+    pub bp: i64,
+    pub input: Vec<i64>,
+    pub output: Vec<i64>,
+}
+
+impl Snapshot {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_unsigned(writer, self.ip as u64)?;
+        write_signed(writer, self.bp)?;
+        write_memory(writer, &self.memory)?;
+        write_words(writer, &self.input)?;
+        write_words(writer, &self.output)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Snapshot> {
+        let mut magic = [0; 4];
+
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an Intcode snapshot",
+            ));
+        }
+
+        Ok(Snapshot {
+            ip: read_unsigned(reader)? as usize,
+            bp: read_signed(reader)?,
+            memory: read_memory(reader)?,
+            input: read_words(reader)?,
+            output: read_words(reader)?,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: &str) -> io::Result<Snapshot> {
+        Snapshot::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+impl<M: Memory<Word = i64>> Intcomp<M> {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: PagedMemory::copy_of(&self.memory),
+            ip: self.ip,
+            bp: self.bp,
+            input: self.input.iter().cloned().collect(),
+            output: self.output.iter().cloned().collect(),
+        }
+    }
+
+    /// Return the machine to the state it was in when `snapshot` was taken.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.memory = M::copy_of(&snapshot.memory);
+        self.ip = snapshot.ip;
+        self.bp = snapshot.bp;
+        self.input = snapshot.input.iter().cloned().collect();
+        self.output = snapshot.output.iter().cloned().collect();
+    }
+}
+
+impl<M: Memory<Word = i64>> From<&Snapshot> for Intcomp<M> {
+    fn from(snapshot: &Snapshot) -> Intcomp<M> {
+        let mut intcomp = Intcomp::with_memory(M::load(&[]));
+
+        intcomp.restore(snapshot);
+
+        intcomp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcomp::memory::{DenseMemory, PagedMemory};
+
+    // Reads two inputs and outputs their sum, twice over.
+    const PROGRAM: [i64; 15] = [3, 14, 3, 13, 1, 13, 14, 13, 4, 13, 1105, 1, 0, 0, 0];
+
+    #[test]
+    fn snapshot_restore_repeats_run() {
+        let mut intcomp = Intcomp::new(&PROGRAM);
+
+        intcomp.send_input(1);
+        intcomp.send_input(2);
+        intcomp.execute().expect("execution failed");
+
+        let snapshot = intcomp.snapshot();
+
+        intcomp.send_input(10);
+        intcomp.send_input(20);
+        intcomp.execute().expect("execution failed");
+
+        let first = intcomp.snapshot();
+
+        intcomp.restore(&snapshot);
+        intcomp.send_input(10);
+        intcomp.send_input(20);
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(first, intcomp.snapshot());
+        assert_eq!(vec![3, 30], first.output);
+    }
+
+    #[test]
+    fn snapshot_includes_queues() {
+        let mut intcomp = Intcomp::new(&PROGRAM);
+
+        intcomp.send_input(1);
+        intcomp.send_input(2);
+        intcomp.send_input(3);
+
+        assert_eq!(vec![1, 2, 3], intcomp.snapshot().input);
+
+        intcomp.execute().expect("execution failed");
+
+        assert_eq!(Vec::<i64>::new(), intcomp.snapshot().input);
+        assert_eq!(vec![3], intcomp.snapshot().output);
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let mut intcomp = Intcomp::new(&PROGRAM);
+
+        intcomp.send_input(1);
+        intcomp.send_input(2);
+        intcomp.execute().expect("execution failed");
+
+        let mut fork = intcomp.clone();
+
+        fork.send_input(5);
+        fork.send_input(5);
+        fork.execute().expect("execution failed");
+
+        assert_eq!(Some(3), intcomp.receive_output());
+        assert_eq!(None, intcomp.receive_output());
+        assert_eq!(Some(3), fork.receive_output());
+        assert_eq!(Some(10), fork.receive_output());
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_bytes() {
+        let mut intcomp = Intcomp::new(&PROGRAM);
+
+        intcomp.send_input(-1);
+        intcomp.send_input(2);
+        intcomp.send_input(7);
+        intcomp.execute().expect("execution failed");
+
+        let snapshot = intcomp.snapshot();
+        let mut bytes = Vec::new();
+
+        snapshot.write_to(&mut bytes).expect("write failed");
+
+        let loaded = Snapshot::read_from(&mut &bytes[..]).expect("read failed");
+
+        assert_eq!(snapshot, loaded);
+        assert_eq!(snapshot, Intcomp::<DenseMemory>::from(&loaded).snapshot());
+    }
+
+    #[test]
+    fn snapshot_of_scattered_memory_stays_sparse() {
+        let mut intcomp =
+            Intcomp::with_memory(PagedMemory::load(&[1101, 2, 3, 1_000_000_000_000, 99]));
+
+        intcomp.execute().expect("execution failed");
+
+        let snapshot = intcomp.snapshot();
+        let mut bytes = Vec::new();
+
+        snapshot.write_to(&mut bytes).expect("write failed");
+
+        assert_eq!(2, snapshot.memory.page_count());
+        assert_eq!(1_000_000_000_001, snapshot.memory.len());
+        assert!(bytes.len() < 64);
+        assert_eq!(
+            snapshot,
+            Snapshot::read_from(&mut &bytes[..]).expect("read failed")
+        );
+        assert_eq!(snapshot, Intcomp::<PagedMemory>::from(&snapshot).snapshot());
+    }
+
+    #[test]
+    fn snapshot_read_rejects_other_files() {
+        assert!(Snapshot::read_from(&mut &b"ICT2"[..]).is_err());
+    }
+}
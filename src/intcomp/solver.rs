@@ -0,0 +1,818 @@
+use super::{Instruction, Intcomp, OperandMode, RunState};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+/// A value computed by a symbolic run: a number, one of the solver's
+/// unknowns, or a combination of them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Constant(i64),
+
+    /// The unknown with this index, in the order they were added.
+    Unknown(usize),
+
+    Add(Box<Expr>, Box<Expr>),
+    Multiply(Box<Expr>, Box<Expr>),
+    LessThan(Box<Expr>, Box<Expr>),
+    Equals(Box<Expr>, Box<Expr>),
+
+    /// Whatever was in memory at an address which depends on the unknowns.
+    Load(Box<Expr>),
+}
+
+impl Expr {
+    fn add(left: Expr, right: Expr) -> Option<Expr> {
+        Some(match (left, right) {
+            (Expr::Constant(left), Expr::Constant(right)) => {
+                Expr::Constant(left.checked_add(right)?)
+            }
+            (left, right) => Expr::Add(Box::new(left), Box::new(right)),
+        })
+    }
+
+    fn multiply(left: Expr, right: Expr) -> Option<Expr> {
+        Some(match (left, right) {
+            (Expr::Constant(left), Expr::Constant(right)) => {
+                Expr::Constant(left.checked_mul(right)?)
+            }
+            (left, right) => Expr::Multiply(Box::new(left), Box::new(right)),
+        })
+    }
+
+    fn less_than(left: Expr, right: Expr) -> Expr {
+        match (left, right) {
+            (Expr::Constant(left), Expr::Constant(right)) => Expr::Constant((left < right) as i64),
+            (left, right) => Expr::LessThan(Box::new(left), Box::new(right)),
+        }
+    }
+
+    fn equals(left: Expr, right: Expr) -> Expr {
+        match (left, right) {
+            (Expr::Constant(left), Expr::Constant(right)) => Expr::Constant((left == right) as i64),
+            (left, right) => Expr::Equals(Box::new(left), Box::new(right)),
+        }
+    }
+
+    fn constant(&self) -> Option<i64> {
+        match self {
+            Expr::Constant(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Rewrite the expression as a constant plus a multiple of each of
+    /// `unknowns` unknowns, if it has that form.
+    fn linear(&self, unknowns: usize) -> Option<Linear> {
+        match self {
+            Expr::Constant(value) => Some(Linear {
+                constant: *value,
+                coefficients: vec![0; unknowns],
+            }),
+
+            Expr::Unknown(index) => {
+                let mut coefficients = vec![0; unknowns];
+
+                coefficients[*index] = 1;
+
+                Some(Linear {
+                    constant: 0,
+                    coefficients,
+                })
+            }
+
+            Expr::Add(left, right) => {
+                let left = left.linear(unknowns)?;
+                let right = right.linear(unknowns)?;
+
+                Some(Linear {
+                    constant: left.constant.checked_add(right.constant)?,
+                    coefficients: left
+                        .coefficients
+                        .iter()
+                        .zip(&right.coefficients)
+                        .map(|(left, right)| left.checked_add(*right))
+                        .collect::<Option<_>>()?,
+                })
+            }
+
+            Expr::Multiply(left, right) => {
+                let (factor, other) = match (left.constant(), right.constant()) {
+                    (Some(factor), _) => (factor, right),
+                    (_, Some(factor)) => (factor, left),
+                    _ => return None,
+                };
+
+                let other = other.linear(unknowns)?;
+
+                Some(Linear {
+                    constant: other.constant.checked_mul(factor)?,
+                    coefficients: other
+                        .coefficients
+                        .iter()
+                        .map(|coefficient| coefficient.checked_mul(factor))
+                        .collect::<Option<_>>()?,
+                })
+            }
+
+            Expr::LessThan(_, _) | Expr::Equals(_, _) | Expr::Load(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Constant(value) => write!(f, "{}", value),
+            Expr::Unknown(index) => write!(f, "x{}", index),
+            Expr::Add(left, right) => write!(f, "({} + {})", left, right),
+            Expr::Multiply(left, right) => write!(f, "({} * {})", left, right),
+            Expr::LessThan(left, right) => write!(f, "({} < {})", left, right),
+            Expr::Equals(left, right) => write!(f, "({} == {})", left, right),
+            Expr::Load(address) => write!(f, "[{}]", address),
+        }
+    }
+}
+
+/// `constant + coefficients[0] * x0 + coefficients[1] * x1 + ...`
+struct Linear {
+    constant: i64,
+    coefficients: Vec<i64>,
+}
+
+/// A machine whose memory holds expressions rather than numbers.  It can only
+/// run as long as control flow and the addresses written to don't depend on
+/// the unknowns.
+struct Symbolic {
+    memory: Vec<Expr>,
+    ip: usize,
+    bp: i64,
+
+    /// Unknowns standing in for inputs, in the order they'll be read.
+    inputs: Vec<usize>,
+}
+
+impl Symbolic {
+    fn read(&self, address: usize) -> Expr {
+        self.memory
+            .get(address)
+            .cloned()
+            .unwrap_or(Expr::Constant(0))
+    }
+
+    fn write(&mut self, address: usize, value: Expr) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, Expr::Constant(0));
+        }
+
+        self.memory[address] = value;
+    }
+
+    /// Resolve the address an operand refers to, if it's known.
+    fn address(&self, mode: OperandMode, operand_address: usize) -> Option<usize> {
+        // An immediate operand is its own address, whatever its value.
+        if mode == OperandMode::Immediate {
+            return Some(operand_address);
+        }
+
+        let operand = self.read(operand_address).constant()?;
+
+        let address = match mode {
+            OperandMode::Relative => self.bp.checked_add(operand)?,
+            _ => operand,
+        };
+
+        if address < 0 {
+            return None;
+        }
+
+        Some(address as usize)
+    }
+
+    /// Read an operand.  Reads from unknown addresses give a `Load`, which is
+    /// fine so long as the value never matters.
+    fn value(&self, mode: OperandMode, operand_address: usize) -> Expr {
+        match self.address(mode, operand_address) {
+            Some(address) => self.read(address),
+            None => Expr::Load(Box::new(self.read(operand_address))),
+        }
+    }
+
+    fn target(&self, mode: OperandMode, operand_address: usize) -> Option<usize> {
+        match mode {
+            OperandMode::Immediate => None,
+            _ => self.address(mode, operand_address),
+        }
+    }
+
+    fn jump(&mut self, condition: bool, target: Expr) -> Option<()> {
+        if !condition {
+            self.ip += 3;
+            return Some(());
+        }
+
+        match target.constant()? {
+            target if target >= 0 => self.ip = target as usize,
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    /// Execute one instruction, returning `Some(true)` once the program has
+    /// halted and `None` if it can't go on symbolically.
+    fn step(&mut self) -> Option<bool> {
+        let ip = self.ip;
+        let instruction = Instruction::parse(self.read(ip).constant()?, ip).ok()?;
+
+        match instruction {
+            Instruction::Add(left, right, target)
+            | Instruction::Multiply(left, right, target)
+            | Instruction::LessThan(left, right, target)
+            | Instruction::Equals(left, right, target) => {
+                let left = self.value(left, ip + 1);
+                let right = self.value(right, ip + 2);
+                let target = self.target(target, ip + 3)?;
+
+                let result = match instruction {
+                    Instruction::Add(_, _, _) => Expr::add(left, right)?,
+                    Instruction::Multiply(_, _, _) => Expr::multiply(left, right)?,
+                    Instruction::LessThan(_, _, _) => Expr::less_than(left, right),
+                    _ => Expr::equals(left, right),
+                };
+
+                self.write(target, result);
+                self.ip += 4;
+            }
+
+            Instruction::Input(target) => {
+                let target = self.target(target, ip + 1)?;
+
+                if self.inputs.is_empty() {
+                    return None;
+                }
+
+                let input = self.inputs.remove(0);
+
+                self.write(target, Expr::Unknown(input));
+                self.ip += 2;
+            }
+
+            Instruction::Output(_) => self.ip += 2,
+
+            Instruction::JumpIfTrue(condition, target) => {
+                let condition = self.value(condition, ip + 1).constant()?;
+                let target = self.value(target, ip + 2);
+
+                self.jump(condition != 0, target)?;
+            }
+
+            Instruction::JumpIfFalse(condition, target) => {
+                let condition = self.value(condition, ip + 1).constant()?;
+                let target = self.value(target, ip + 2);
+
+                self.jump(condition == 0, target)?;
+            }
+
+            Instruction::AdjustRelativeBase(offset) => {
+                let offset = self.value(offset, ip + 1).constant()?;
+
+                self.bp = self.bp.checked_add(offset)?;
+                self.ip += 2;
+            }
+
+            Instruction::Halt => return Some(true),
+        }
+
+        Some(false)
+    }
+}
+
+/// Where the value of an unknown goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Location {
+    Memory(usize),
+
+    /// The next input the program reads.  Input unknowns are read in the
+    /// order they were added.
+    Input,
+}
+
+/// How a solution was found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    /// By solving the linear expression the target works out to be.
+    Linear,
+
+    /// By trying every combination of values.
+    BruteForce,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Solution {
+    /// The value of each unknown, in the order they were added.
+    pub values: Vec<i64>,
+
+    pub method: Method,
+}
+
+/// Finds values for some of a program's memory cells and inputs which leave a
+/// chosen value at a target address when it halts.
+///
+/// The program is first run symbolically.  If that shows the target to be
+/// linear in the unknowns, it's solved for directly; otherwise every
+/// combination of values is tried, spread across threads.
+pub struct Solver {
+    program: Vec<i64>,
+    unknowns: Vec<(Location, RangeInclusive<i64>)>,
+    step_limit: usize,
+}
+
+impl Solver {
+    pub fn new(program: &[i64]) -> Solver {
+        Solver {
+            program: program.to_vec(),
+            unknowns: Vec::new(),
+            step_limit: 1_000_000,
+        }
+    }
+
+    /// Add an unknown for the memory cell at `address`, which may take any of
+    /// `values`.  Returns its index.
+    pub fn patch(&mut self, address: usize, values: RangeInclusive<i64>) -> usize {
+        self.unknowns.push((Location::Memory(address), values));
+        self.unknowns.len() - 1
+    }
+
+    /// Add an unknown for the program's next input.  Returns its index.
+    pub fn input(&mut self, values: RangeInclusive<i64>) -> usize {
+        self.unknowns.push((Location::Input, values));
+        self.unknowns.len() - 1
+    }
+
+    /// Set how many instructions a run may take before it's given up on.
+    /// Defaults to a million.
+    pub fn set_step_limit(&mut self, steps: usize) {
+        self.step_limit = steps;
+    }
+
+    /// The value left at `address` when the program halts, in terms of the
+    /// unknowns.  `None` if the program can't be run symbolically, e.g.
+    /// because it branches on an unknown.
+    pub fn expression(&self, address: usize) -> Option<Expr> {
+        let mut symbolic = Symbolic {
+            memory: self.program.iter().cloned().map(Expr::Constant).collect(),
+            ip: 0,
+            bp: 0,
+            inputs: Vec::new(),
+        };
+
+        for (index, (location, _)) in self.unknowns.iter().enumerate() {
+            match location {
+                Location::Memory(address) => symbolic.write(*address, Expr::Unknown(index)),
+                Location::Input => symbolic.inputs.push(index),
+            }
+        }
+
+        for _ in 0..self.step_limit {
+            if symbolic.step()? {
+                return Some(symbolic.read(address));
+            }
+        }
+
+        None
+    }
+
+    /// Find values for the unknowns which leave `target` at `address`.  When
+    /// there's more than one solution, brute force finds the first in order
+    /// of the unknowns' values, the first unknown varying slowest.
+    pub fn solve(&self, address: usize, target: i64) -> Option<Solution> {
+        if let Some(solution) = self.solve_linear(address, target) {
+            return solution.map(|values| Solution {
+                values,
+                method: Method::Linear,
+            });
+        }
+
+        self.brute_force(address, target).map(|values| Solution {
+            values,
+            method: Method::BruteForce,
+        })
+    }
+
+    /// `None` if the target isn't linear in the unknowns, otherwise whether
+    /// there's a solution.  The last two unknowns the target depends on are
+    /// solved for directly, trying every combination of any others, so the
+    /// usual one or two unknowns take no search at all.
+    fn solve_linear(&self, address: usize, target: i64) -> Option<Option<Vec<i64>>> {
+        let linear = self.expression(address)?.linear(self.unknowns.len())?;
+
+        let ranges = self
+            .unknowns
+            .iter()
+            .map(|(_, values)| values.clone())
+            .collect::<Vec<_>>();
+
+        // Unknowns which don't affect the target can take any value.
+        let mut values = ranges
+            .iter()
+            .map(|values| *values.start())
+            .collect::<Vec<_>>();
+
+        if ranges.iter().any(|values| values.is_empty()) {
+            return Some(None);
+        }
+
+        let dependencies = (0..ranges.len())
+            .filter(|index| linear.coefficients[*index] != 0)
+            .collect::<Vec<_>>();
+
+        let (others, solve_for) = dependencies.split_at(dependencies.len().saturating_sub(2));
+
+        let other_ranges = others
+            .iter()
+            .map(|index| ranges[*index].clone())
+            .collect::<Vec<_>>();
+
+        let equation = solve_for
+            .iter()
+            .map(|index| {
+                let range = &ranges[*index];
+
+                (
+                    linear.coefficients[*index] as i128,
+                    *range.start() as i128,
+                    *range.end() as i128,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for candidate in 0..candidates(&other_ranges) {
+            let mut remainder = target as i128 - linear.constant as i128;
+
+            for (index, value) in others.iter().zip(nth(&other_ranges, candidate)) {
+                values[*index] = value;
+                remainder -= linear.coefficients[*index] as i128 * value as i128;
+            }
+
+            let solution = match solve_directly(&equation, remainder) {
+                Some(solution) => solution,
+                None => continue,
+            };
+
+            for (index, value) in solve_for.iter().zip(solution) {
+                values[*index] = value;
+            }
+
+            // Arithmetic which overflows when run for real isn't captured by
+            // the expression, so don't trust it unless a real run agrees.
+            if !self.check(&values, address, target) {
+                return None;
+            }
+
+            return Some(Some(values));
+        }
+
+        Some(None)
+    }
+
+    fn brute_force(&self, address: usize, target: i64) -> Option<Vec<i64>> {
+        let ranges = self
+            .unknowns
+            .iter()
+            .map(|(_, values)| values.clone())
+            .collect::<Vec<_>>();
+
+        let count = candidates(&ranges);
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get()) as u128;
+        let first = Mutex::new(u128::MAX);
+
+        thread::scope(|scope| {
+            for thread in 0..threads {
+                let (ranges, first) = (&ranges, &first);
+
+                scope.spawn(move || {
+                    let mut candidate = thread;
+
+                    // Candidates after one which has already been found
+                    // can't be the first.
+                    while candidate < count && candidate < *lock(first) {
+                        if self.check(&nth(ranges, candidate), address, target) {
+                            let mut first = lock(first);
+
+                            *first = u128::min(*first, candidate);
+                        }
+
+                        candidate += threads;
+                    }
+                });
+            }
+        });
+
+        match first.into_inner().expect("solver thread panicked") {
+            u128::MAX => None,
+            candidate => Some(nth(&ranges, candidate)),
+        }
+    }
+
+    /// Run the program for real with the given values for the unknowns.
+    fn check(&self, values: &[i64], address: usize, target: i64) -> bool {
+        let mut intcomp = Intcomp::new(&self.program);
+
+        for ((location, _), value) in self.unknowns.iter().zip(values) {
+            match location {
+                Location::Memory(address) => intcomp.write_memory(*address, *value),
+                Location::Input => intcomp.send_input(*value),
+            }
+        }
+
+        intcomp.run_for(self.step_limit) == Ok(RunState::Halted)
+            && intcomp.read_memory(address) == target
+    }
+}
+
+/// Solve `sum(coefficient * x) = remainder` for at most two unknowns, each
+/// given as `(coefficient, lowest, highest)`, with none of the coefficients
+/// zero.  When there's more than one solution, the first unknown is as small
+/// as it can be.
+fn solve_directly(equation: &[(i128, i128, i128)], remainder: i128) -> Option<Vec<i64>> {
+    match *equation {
+        [] if remainder == 0 => Some(Vec::new()),
+        [] => None,
+
+        [(a, low, high)] => {
+            let x = remainder / a;
+
+            if remainder % a == 0 && (low..=high).contains(&x) {
+                Some(vec![x as i64])
+            } else {
+                None
+            }
+        }
+
+        [(a, x_low, x_high), (b, y_low, y_high)] => {
+            let (g, u) = extended_gcd(a, b);
+
+            if remainder % g != 0 {
+                return None;
+            }
+
+            // Every solution has x = x0 + step * t for some t.  Reducing x0
+            // modulo the step keeps everything well inside an i128.
+            let step = (b / g).abs();
+            let x0 = ((u % step) * ((remainder / g) % step)).rem_euclid(step);
+
+            // The bounds on y, as bounds on a * x and then on x.
+            let ends = (remainder - b * y_low, remainder - b * y_high);
+            let (low, high) = (ends.0.min(ends.1), ends.0.max(ends.1));
+
+            let (low, high) = if a > 0 {
+                (ceil_div(low, a), floor_div(high, a))
+            } else {
+                (ceil_div(high, a), floor_div(low, a))
+            };
+
+            let x = x0 + step * ceil_div(low.max(x_low) - x0, step);
+
+            if x <= high.min(x_high) {
+                Some(vec![x as i64, ((remainder - a * x) / b) as i64])
+            } else {
+                None
+            }
+        }
+
+        _ => unreachable!("at most two unknowns are solved directly"),
+    }
+}
+
+/// The greatest common divisor `g` of `a` and `b`, with `u` such that
+/// `a * u + b * v = g` for some `v`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128) {
+    let (mut g, mut next) = (a, b);
+    let (mut u, mut next_u) = (1, 0);
+
+    while next != 0 {
+        let quotient = g / next;
+
+        (g, next) = (next, g - quotient * next);
+        (u, next_u) = (next_u, u - quotient * next_u);
+    }
+
+    (g, u)
+}
+
+fn floor_div(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator / denominator;
+
+    if numerator % denominator != 0 && (numerator < 0) != (denominator < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn ceil_div(numerator: i128, denominator: i128) -> i128 {
+    -floor_div(-numerator, denominator)
+}
+
+fn lock(first: &Mutex<u128>) -> MutexGuard<'_, u128> {
+    first.lock().expect("solver thread panicked")
+}
+
+/// The number of values a range allows.  A range covering every `i64` has
+/// 2^64 of them, which is why this is a `u128`.
+fn size(values: &RangeInclusive<i64>) -> u128 {
+    (*values.end() as i128 - *values.start() as i128 + 1).max(0) as u128
+}
+
+/// The number of combinations of values the ranges allow, saturating rather
+/// than overflowing.
+fn candidates(ranges: &[RangeInclusive<i64>]) -> u128 {
+    ranges.iter().map(size).fold(1, u128::saturating_mul)
+}
+
+/// The combination of values numbered `candidate`, counting with the last
+/// range varying fastest.
+fn nth(ranges: &[RangeInclusive<i64>], mut candidate: u128) -> Vec<i64> {
+    let mut values = vec![0; ranges.len()];
+
+    for (value, range) in values.iter_mut().zip(ranges).rev() {
+        let size = size(range);
+
+        *value = (*range.start() as i128 + (candidate % size) as i128) as i64;
+        candidate /= size;
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Works like a Day 2 program: the first instruction reads from the
+    /// addresses being patched, then [0] = [1] * 3 + [2] + 5.
+    const LINEAR: [i64; 19] = [
+        1, 0, 0, 0, 1002, 1, 3, 0, 1, 0, 2, 0, 1001, 0, 5, 0, 99, 0, 0,
+    ];
+
+    #[test]
+    fn solver_builds_expressions() {
+        let mut solver = Solver::new(&LINEAR);
+
+        solver.patch(1, 0..=9);
+        solver.patch(2, 0..=9);
+
+        assert_eq!(
+            "(((x0 * 3) + x1) + 5)",
+            solver.expression(0).expect("not symbolic").to_string()
+        );
+    }
+
+    #[test]
+    fn solver_solves_linear_targets() {
+        let mut solver = Solver::new(&LINEAR);
+
+        solver.patch(1, 0..=9);
+        solver.patch(2, 0..=9);
+
+        assert_eq!(
+            Some(Solution {
+                values: vec![4, 7],
+                method: Method::Linear,
+            }),
+            solver.solve(0, 24)
+        );
+        assert_eq!(None, solver.solve(0, 100));
+    }
+
+    #[test]
+    fn solver_solves_wide_ranges_directly() {
+        // [0] = [1] + [2].
+        let mut sum = Solver::new(&[1101, 0, 0, 0, 99]);
+
+        sum.patch(1, 0..=i64::MAX / 4);
+        sum.patch(2, 0..=i64::MAX / 4);
+
+        assert_eq!(None, sum.solve(0, -1));
+        assert_eq!(
+            Some(Solution {
+                values: vec![1000, i64::MAX / 4],
+                method: Method::Linear,
+            }),
+            sum.solve(0, i64::MAX / 4 + 1000)
+        );
+
+        // [0] = 2 * [2] + 4 * [6], which is always even.
+        let mut even = Solver::new(&[1102, 2, 0, 13, 1102, 4, 0, 14, 1, 13, 14, 0, 99, 0, 0]);
+
+        even.patch(2, 0..=i64::MAX / 4);
+        even.patch(6, 0..=i64::MAX / 4);
+
+        assert_eq!(None, even.solve(0, 1_000_001));
+        assert_eq!(
+            Some(Solution {
+                values: vec![1, 250_000],
+                method: Method::Linear,
+            }),
+            even.solve(0, 1_000_002)
+        );
+    }
+
+    #[test]
+    fn solver_solves_mixed_signs() {
+        // [0] = [1] * 3 - [2] * 5 + 5, as LINEAR but subtracting.
+        let mut solver = Solver::new(&[
+            1102, 0, 3, 17, 1102, 0, -5, 18, 1, 17, 18, 19, 1001, 19, 5, 0, 99, 0, 0, 0,
+        ]);
+
+        solver.patch(1, -10..=10);
+        solver.patch(5, -10..=10);
+
+        // 3x - 5y = 2 has x = 4 + 5t, y = 2 + 3t, and t = -2 is the first in range.
+        assert_eq!(
+            Some(Solution {
+                values: vec![-6, -4],
+                method: Method::Linear,
+            }),
+            solver.solve(0, 7)
+        );
+    }
+
+    #[test]
+    fn solver_brute_forces_branching_programs() {
+        // Leaves [14] = 1 if its input is less than 7, checked with a jump
+        // rather than arithmetic.
+        let program = [
+            3, 15, 1007, 15, 7, 16, 1006, 16, 13, 1101, 0, 1, 14, 99, 0, 0, 0,
+        ];
+        let mut solver = Solver::new(&program);
+
+        solver.input(3..=20);
+
+        assert_eq!(None, solver.expression(14));
+        assert_eq!(
+            Some(Solution {
+                values: vec![3],
+                method: Method::BruteForce,
+            }),
+            solver.solve(14, 1)
+        );
+    }
+
+    #[test]
+    fn solver_brute_force_finds_first_solution() {
+        // [0] = [5] * [6], which isn't linear.
+        let mut solver = Solver::new(&[2, 5, 6, 0, 99, 0, 0]);
+
+        solver.patch(5, 1..=12);
+        solver.patch(6, 1..=12);
+
+        assert_eq!(
+            Some(Solution {
+                values: vec![2, 12],
+                method: Method::BruteForce,
+            }),
+            solver.solve(0, 24)
+        );
+    }
+
+    #[test]
+    fn solver_counts_candidates() {
+        assert_eq!(6, candidates(&[0..=1, 5..=7]));
+        assert_eq!(vec![1, 6], nth(&[0..=1, 5..=7], 4));
+        assert_eq!(1 << 64, candidates(&[i64::MIN..=i64::MAX]));
+        assert_eq!(
+            vec![i64::MAX],
+            nth(&[i64::MIN..=i64::MAX], u64::MAX as u128)
+        );
+    }
+
+    #[test]
+    fn solver_handles_full_ranges() {
+        // [0] = [1] + [2].
+        let mut solver = Solver::new(&[1101, 0, 0, 0, 99]);
+
+        solver.patch(1, i64::MIN..=i64::MAX);
+        solver.patch(2, i64::MIN..=i64::MAX);
+
+        assert_eq!(
+            Some(Solution {
+                values: vec![i64::MIN, i64::MAX],
+                method: Method::Linear,
+            }),
+            solver.solve(0, -1)
+        );
+    }
+
+    #[test]
+    fn solver_rejects_overflowing_division() {
+        // [0] = -1 * [2], which can never be i64::MIN.
+        let mut solver = Solver::new(&[1102, -1, 0, 0, 99]);
+
+        solver.patch(2, i64::MIN..=i64::MAX);
+
+        assert_eq!(None, solver.solve(0, i64::MIN));
+    }
+}
@@ -0,0 +1,491 @@
+use super::memory::{Memory, PagedMemory};
+use super::varint::{
+    read_memory, read_signed, read_unsigned, write_memory, write_signed, write_unsigned,
+};
+use super::{Hook, Instruction, Intcomp, IntcompError};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+
+const MAGIC: &[u8; 4] = b"ICT2";
+
+/// Everything that happened when a single instruction executed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceStep {
+    pub ip: usize,
+
+    /// The undecoded instruction, as it was when it executed.
+    pub instruction: i64,
+
+    /// The value of each operand read, in order.
+    pub reads: Vec<i64>,
+
+    /// The address and value written, if the instruction wrote anything.
+    pub write: Option<(usize, i64)>,
+}
+
+/// A record of a program's execution from a known starting state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace {
+    pub memory: PagedMemory,
+    pub ip: usize,
+    pub bp: i64,
+    pub steps: Vec<TraceStep>,
+}
+
+impl Trace {
+    fn decode(step: &TraceStep) -> Result<Instruction, IntcompError> {
+        Instruction::parse(step.instruction, step.ip)
+    }
+
+    /// The inputs the program consumed, in order.
+    pub fn inputs(&self) -> Vec<i64> {
+        self.steps
+            .iter()
+            .filter(|step| matches!(Trace::decode(step), Ok(Instruction::Input(_))))
+            .filter_map(|step| step.write.map(|(_, value)| value))
+            .collect()
+    }
+
+    /// The outputs the program produced, in order.
+    pub fn outputs(&self) -> Vec<i64> {
+        self.steps
+            .iter()
+            .filter(|step| matches!(Trace::decode(step), Ok(Instruction::Output(_))))
+            .filter_map(|step| step.reads.first().cloned())
+            .collect()
+    }
+
+    /// The index of the first step at which two traces differ, or `None` if
+    /// they're identical.  A trace which is a prefix of the other differs at
+    /// the step where it ends.
+    pub fn first_divergence(&self, other: &Trace) -> Option<usize> {
+        if (&self.memory, self.ip, self.bp) != (&other.memory, other.ip, other.bp) {
+            return Some(0);
+        }
+
+        match self
+            .steps
+            .iter()
+            .zip(&other.steps)
+            .position(|(a, b)| a != b)
+        {
+            Some(index) => Some(index),
+            None if self.steps.len() != other.steps.len() => {
+                Some(usize::min(self.steps.len(), other.steps.len()))
+            }
+            None => None,
+        }
+    }
+
+    /// Rebuild the machine as it was after the first `steps` steps.  Outputs
+    /// produced up to that point are left in its output queue.  Memory stays
+    /// sparse, as it is in the trace.
+    ///
+    /// Traces may come from anywhere, so every step is checked as the machine
+    /// would check it, with any memory limit applied to the trace's memory
+    /// and writes.  A step which doesn't add up fails with the error the
+    /// machine would have given.
+    pub fn replay(
+        &self,
+        steps: usize,
+        memory_limit: Option<usize>,
+    ) -> Result<Intcomp<PagedMemory>, IntcompError> {
+        let mut intcomp = Intcomp::with_memory(self.memory.clone());
+
+        intcomp.set_memory_limit(memory_limit);
+        intcomp.ip = self.ip;
+        intcomp.bp = self.bp;
+
+        if let Some((address, _)) = self.memory.nonzero().last() {
+            intcomp.check_address(*address as i64)?;
+        }
+
+        for step in self.steps.iter().take(steps) {
+            let instruction = Trace::decode(step)?;
+            let read = |index: usize| step.reads.get(index).cloned().unwrap_or(0);
+            let overflow = || IntcompError::Overflow { address: step.ip };
+
+            intcomp.ip = step.ip;
+
+            if let Some((address, value)) = step.write {
+                let address = i64::try_from(address).map_err(|_| overflow())?;
+                let address = intcomp.check_address(address)?;
+
+                intcomp.memory.write(address, value);
+            }
+
+            match instruction {
+                Instruction::Output(_) => intcomp.output.push_back(read(0)),
+
+                Instruction::AdjustRelativeBase(_) => {
+                    intcomp.bp = intcomp.bp.checked_add(read(0)).ok_or_else(overflow)?
+                }
+
+                _ => {}
+            }
+
+            intcomp.ip = match instruction {
+                Instruction::JumpIfTrue(_, _) if read(0) != 0 => intcomp.check_address(read(1))?,
+                Instruction::JumpIfFalse(_, _) if read(0) == 0 => intcomp.check_address(read(1))?,
+                Instruction::Halt => step.ip,
+                _ => step
+                    .ip
+                    .checked_add(instruction.width())
+                    .ok_or_else(overflow)?,
+            };
+        }
+
+        Ok(intcomp)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_unsigned(writer, self.ip as u64)?;
+        write_signed(writer, self.bp)?;
+        write_memory(writer, &self.memory)?;
+
+        write_unsigned(writer, self.steps.len() as u64)?;
+
+        for step in &self.steps {
+            write_unsigned(writer, step.ip as u64)?;
+            write_signed(writer, step.instruction)?;
+            write_unsigned(writer, step.reads.len() as u64)?;
+
+            for value in &step.reads {
+                write_signed(writer, *value)?;
+            }
+
+            match step.write {
+                Some((address, value)) => {
+                    write_unsigned(writer, 1)?;
+                    write_unsigned(writer, address as u64)?;
+                    write_signed(writer, value)?;
+                }
+
+                None => write_unsigned(writer, 0)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Trace> {
+        let mut magic = [0; 4];
+
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an Intcode trace",
+            ));
+        }
+
+        let ip = read_unsigned(reader)? as usize;
+        let bp = read_signed(reader)?;
+        let memory = read_memory(reader)?;
+
+        let mut steps = Vec::new();
+
+        for _ in 0..read_unsigned(reader)? {
+            let ip = read_unsigned(reader)? as usize;
+            let instruction = read_signed(reader)?;
+            let reads = (0..read_unsigned(reader)?)
+                .map(|_| read_signed(reader))
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let write = match read_unsigned(reader)? {
+                0 => None,
+                _ => Some((read_unsigned(reader)? as usize, read_signed(reader)?)),
+            };
+
+            steps.push(TraceStep {
+                ip,
+                instruction,
+                reads,
+                write,
+            });
+        }
+
+        Ok(Trace {
+            memory,
+            ip,
+            bp,
+            steps,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: &str) -> io::Result<Trace> {
+        Trace::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+/// A `Hook` which records every instruction executed.  Tracing is opt-in:
+/// pass a `Tracer` to `Intcomp::execute_with` to use it.
+pub struct Tracer {
+    trace: Trace,
+    pending: TraceStep,
+}
+
+impl Tracer {
+    /// Start recording from `intcomp`'s current state.
+    pub fn new<M: Memory<Word = i64>>(intcomp: &Intcomp<M>) -> Tracer {
+        Tracer {
+            trace: Trace {
+                memory: PagedMemory::copy_of(&intcomp.memory),
+                ip: intcomp.ip,
+                bp: intcomp.bp,
+                steps: Vec::new(),
+            },
+
+            pending: TraceStep::default(),
+        }
+    }
+
+    pub fn into_trace(self) -> Trace {
+        self.trace
+    }
+}
+
+impl Hook for Tracer {
+    fn before_instruction(&mut self, _ip: usize, _bp: i64) -> bool {
+        self.pending = TraceStep::default();
+
+        true
+    }
+
+    fn after_read(&mut self, _address: usize, value: i64) {
+        self.pending.reads.push(value);
+    }
+
+    fn before_write(&mut self, address: usize, value: i64) -> bool {
+        self.pending.write = Some((address, value));
+
+        true
+    }
+
+    fn after_instruction(&mut self, ip: usize, instruction: i64) {
+        let mut step = mem::take(&mut self.pending);
+
+        step.ip = ip;
+        step.instruction = instruction;
+
+        self.trace.steps.push(step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(program: &[i64], inputs: &[i64]) -> Trace {
+        let mut intcomp = Intcomp::new(program);
+        let mut tracer = Tracer::new(&intcomp);
+
+        for input in inputs {
+            intcomp.send_input(*input);
+        }
+
+        intcomp.execute_with(&mut tracer).expect("execution failed");
+
+        tracer.into_trace()
+    }
+
+    #[test]
+    fn tracer_records_steps() {
+        let trace = trace(&[3, 9, 1001, 9, 5, 9, 4, 9, 99, 0], &[2]);
+
+        assert_eq!(
+            vec![
+                TraceStep {
+                    ip: 0,
+                    instruction: 3,
+                    reads: vec![],
+                    write: Some((9, 2)),
+                },
+                TraceStep {
+                    ip: 2,
+                    instruction: 1001,
+                    reads: vec![2, 5],
+                    write: Some((9, 7)),
+                },
+                TraceStep {
+                    ip: 6,
+                    instruction: 4,
+                    reads: vec![7],
+                    write: None,
+                },
+                TraceStep {
+                    ip: 8,
+                    instruction: 99,
+                    reads: vec![],
+                    write: None,
+                },
+            ],
+            trace.steps
+        );
+    }
+
+    #[test]
+    fn tracer_skips_instructions_awaiting_input() {
+        let program = vec![3, 5, 4, 5, 99, 0];
+        let mut intcomp = Intcomp::new(&program);
+        let mut tracer = Tracer::new(&intcomp);
+
+        intcomp.execute_with(&mut tracer).expect("execution failed");
+        intcomp.send_input(8);
+        intcomp.execute_with(&mut tracer).expect("execution failed");
+
+        let trace = tracer.into_trace();
+
+        assert_eq!(3, trace.steps.len());
+        assert_eq!(vec![8], trace.inputs());
+        assert_eq!(vec![8], trace.outputs());
+    }
+
+    #[test]
+    fn trace_replay_matches_execution() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let trace = trace(&program, &[]);
+
+        for steps in 0..=trace.steps.len() {
+            let mut expected = Intcomp::new(&program);
+            let mut actual = trace.replay(steps, None).expect("replay failed");
+
+            expected.run_for(steps).expect("execution failed");
+
+            assert_eq!(expected.ip, actual.ip);
+            assert_eq!(expected.bp, actual.bp);
+            assert_eq!(PagedMemory::copy_of(&expected.memory), actual.memory);
+
+            while let Some(output) = expected.receive_output() {
+                assert_eq!(Some(output), actual.receive_output());
+            }
+        }
+    }
+
+    #[test]
+    fn trace_replay_keeps_memory_sparse() {
+        let mut recorded = Intcomp::<PagedMemory>::load(&[1101, 1, 2, 1 << 40, 99]);
+        let mut tracer = Tracer::new(&recorded);
+
+        recorded
+            .execute_with(&mut tracer)
+            .expect("execution failed");
+
+        let trace = tracer.into_trace();
+        let intcomp = trace
+            .replay(trace.steps.len(), None)
+            .expect("replay failed");
+
+        assert_eq!(3, intcomp.read_memory(1 << 40));
+        assert_eq!(2, intcomp.memory.page_count());
+    }
+
+    /// A trace of a single step, as if read from a file.
+    fn crafted(
+        program: &[i64],
+        instruction: i64,
+        reads: &[i64],
+        write: Option<(usize, i64)>,
+    ) -> Trace {
+        Trace {
+            memory: PagedMemory::load(program),
+            ip: 0,
+            bp: 1,
+            steps: vec![TraceStep {
+                ip: 0,
+                instruction,
+                reads: reads.to_vec(),
+                write,
+            }],
+        }
+    }
+
+    #[test]
+    fn trace_replay_rejects_impossible_steps() {
+        let replay = |trace: Trace, limit| trace.replay(1, limit).map(|_| ());
+
+        assert_eq!(
+            Err(IntcompError::Overflow { address: 0 }),
+            replay(crafted(&[109, 1, 99], 109, &[i64::MAX], None), None)
+        );
+        assert_eq!(
+            Err(IntcompError::NegativeAddress {
+                address: 0,
+                target: -5
+            }),
+            replay(crafted(&[1105, 1, -5], 1105, &[1, -5], None), None)
+        );
+        assert_eq!(
+            Err(IntcompError::AddressLimitExceeded {
+                address: 0,
+                target: 1 << 40
+            }),
+            replay(
+                crafted(
+                    &[1101, 1, 2, 1 << 40, 99],
+                    1101,
+                    &[1, 2],
+                    Some((1 << 40, 3))
+                ),
+                Some(4096)
+            )
+        );
+
+        let mut far = crafted(&[99], 1101, &[1, 2], None);
+
+        far.steps[0].ip = usize::MAX;
+
+        assert_eq!(
+            Err(IntcompError::Overflow {
+                address: usize::MAX
+            }),
+            replay(far, None)
+        );
+    }
+
+    #[test]
+    fn trace_round_trips_through_bytes() {
+        let trace = trace(&[3, 9, 1001, 9, -5, 9, 4, 9, 99, 0], &[2]);
+        let mut bytes = Vec::new();
+
+        trace.write_to(&mut bytes).expect("write failed");
+
+        assert_eq!(
+            trace,
+            Trace::read_from(&mut &bytes[..]).expect("read failed")
+        );
+    }
+
+    #[test]
+    fn trace_read_rejects_other_files() {
+        assert!(Trace::read_from(&mut &b"1,2,3,99"[..]).is_err());
+    }
+
+    #[test]
+    fn trace_finds_first_divergence() {
+        let program = vec![3, 9, 1001, 9, 5, 9, 4, 9, 99, 0];
+        let first = trace(&program, &[2]);
+
+        assert_eq!(None, first.first_divergence(&first.clone()));
+        assert_eq!(Some(0), first.first_divergence(&trace(&program, &[3])));
+
+        let mut truncated = first.clone();
+
+        truncated.steps.pop();
+
+        assert_eq!(Some(3), first.first_divergence(&truncated));
+    }
+}
@@ -0,0 +1,148 @@
+use super::memory::{Memory, PagedMemory};
+use std::io::{self, Read, Write};
+
+/// Write `value` as an LEB128 variable-length integer: seven bits per byte,
+/// least significant first, with the high bit set on all but the last byte.
+pub fn write_unsigned<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+
+        value >>= 7;
+
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Write `value` zigzag-encoded, so that small negative numbers stay short.
+pub fn write_signed<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+    write_unsigned(writer, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+pub fn read_unsigned<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+
+    loop {
+        let mut byte = [0];
+
+        reader.read_exact(&mut byte)?;
+
+        if shift >= 64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "variable-length integer is too long",
+            ));
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        shift += 7;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+pub fn read_signed<R: Read>(reader: &mut R) -> io::Result<i64> {
+    let value = read_unsigned(reader)?;
+
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+/// Write a length-prefixed list of signed integers.
+pub fn write_words<W: Write>(writer: &mut W, words: &[i64]) -> io::Result<()> {
+    write_unsigned(writer, words.len() as u64)?;
+
+    for word in words {
+        write_signed(writer, *word)?;
+    }
+
+    Ok(())
+}
+
+pub fn read_words<R: Read>(reader: &mut R) -> io::Result<Vec<i64>> {
+    (0..read_unsigned(reader)?)
+        .map(|_| read_signed(reader))
+        .collect()
+}
+
+/// Write `memory`'s length and then each word which isn't zero, as the gap
+/// since the previous address followed by the value.
+pub fn write_memory<W: Write, M: Memory<Word = i64>>(writer: &mut W, memory: &M) -> io::Result<()> {
+    let words = memory.nonzero();
+    let mut next = 0;
+
+    write_unsigned(writer, memory.len() as u64)?;
+    write_unsigned(writer, words.len() as u64)?;
+
+    for (address, word) in words {
+        write_unsigned(writer, (address - next) as u64)?;
+        write_signed(writer, word)?;
+        next = address + 1;
+    }
+
+    Ok(())
+}
+
+pub fn read_memory<R: Read>(reader: &mut R) -> io::Result<PagedMemory> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "memory address is too large");
+    let len = read_unsigned(reader)? as usize;
+    let mut memory = PagedMemory::default();
+    let mut next = 0_usize;
+
+    for _ in 0..read_unsigned(reader)? {
+        let address = next
+            .checked_add(read_unsigned(reader)? as usize)
+            .filter(|address| *address < len)
+            .ok_or_else(invalid)?;
+
+        memory.write(address, read_signed(reader)?);
+        next = address + 1;
+    }
+
+    memory.grow_to(len);
+
+    Ok(memory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint_round_trips() {
+        let values = vec![0, 1, -1, 63, -64, 64, 300, -300, i64::MAX, i64::MIN];
+        let mut bytes = Vec::new();
+
+        for value in &values {
+            write_signed(&mut bytes, *value).expect("write failed");
+        }
+
+        let mut reader = &bytes[..];
+
+        for value in values {
+            assert_eq!(value, read_signed(&mut reader).expect("read failed"));
+        }
+    }
+
+    #[test]
+    fn varint_keeps_small_values_short() {
+        let mut bytes = Vec::new();
+
+        write_signed(&mut bytes, -1).expect("write failed");
+        write_unsigned(&mut bytes, 127).expect("write failed");
+
+        assert_eq!(vec![1, 127], bytes);
+    }
+
+    #[test]
+    fn varint_rejects_overlong_values() {
+        let bytes = [0xff; 11];
+
+        assert!(read_unsigned(&mut &bytes[..]).is_err());
+    }
+}
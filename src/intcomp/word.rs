@@ -0,0 +1,37 @@
+use std::convert::TryInto;
+use std::fmt::{Debug, Display};
+
+/// A value held in a single memory cell.  Arithmetic on words is checked, so
+/// that a program which overflows fails with an error instead of giving an
+/// answer which depends on how it was built.
+pub trait Word: Copy + Debug + Display + Default + Ord + From<i64> + TryInto<i64> {
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    fn checked_mul(self, other: Self) -> Option<Self>;
+
+    /// The word as an `i64`, if it fits in one.
+    fn to_i64(self) -> Option<i64> {
+        self.try_into().ok()
+    }
+}
+
+impl Word for i64 {
+    fn checked_add(self, other: i64) -> Option<i64> {
+        i64::checked_add(self, other)
+    }
+
+    fn checked_mul(self, other: i64) -> Option<i64> {
+        i64::checked_mul(self, other)
+    }
+}
+
+/// Wide words, for programs whose intermediate values don't fit in an `i64`.
+impl Word for i128 {
+    fn checked_add(self, other: i128) -> Option<i128> {
+        i128::checked_add(self, other)
+    }
+
+    fn checked_mul(self, other: i128) -> Option<i128> {
+        i128::checked_mul(self, other)
+    }
+}
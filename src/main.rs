@@ -15,52 +15,273 @@ mod day14;
 mod day15;
 mod day16;
 mod day17;
-mod intcomp;
 
 extern crate itertools;
 #[cfg(test)]
 extern crate maplit;
 extern crate permutohedron;
 
+use aoc2019::intcomp::analysis::Analysis;
+use aoc2019::intcomp::ascii::AsciiIntcomp;
+use aoc2019::intcomp::compiler::Compiled;
+use aoc2019::intcomp::debugger::Debugger;
+use aoc2019::intcomp::disassembler::disassemble;
+use aoc2019::intcomp::io::StdIo;
+use aoc2019::intcomp::loader::{load_program, parse_program, save_binary};
+use aoc2019::intcomp::profiler::Profiler;
+use aoc2019::intcomp::{self, read_program, Intcomp, IntcompError};
 use std::env;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+/// Run the ASCII Intcode program at `path`, connected to the terminal.
+fn interact(path: &str) {
+    let stdin = io::stdin();
+
+    if let Err(error) = AsciiIntcomp::new(&read_program(path)).interact(stdin.lock(), io::stdout())
+    {
+        eprintln!("Interaction failed: {}", error);
+    }
+}
+
+/// Compile each Intcode program named `dayNN.input.txt` in the current
+/// directory to a Rust module `dayNN.rs` in `output`, along with a `mod.rs`
+/// declaring them.  Inputs which aren't Intcode are skipped.
+fn compile(output: &str) -> io::Result<()> {
+    let mut days = fs::read_dir(".")?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| Some(name.strip_suffix(".input.txt")?.to_string()))
+        .filter(|day| day.starts_with("day"))
+        .collect::<Vec<_>>();
+
+    days.sort();
+    fs::create_dir_all(output)?;
+
+    let mut modules = String::new();
+
+    for day in days {
+        let program = match load_program(&format!("{}.input.txt", day)) {
+            Ok(program) => program,
+            Err(_) => continue,
+        };
+
+        fs::write(
+            Path::new(output).join(format!("{}.rs", day)),
+            Compiled::new(&program).to_rust(),
+        )?;
+
+        modules.push_str(&format!("pub mod {};\n", day));
+    }
+
+    fs::write(Path::new(output).join("mod.rs"), modules)
+}
+
+/// Run the Intcode program at `path` under the interactive debugger.
+fn debug(path: &str) {
+    let stdin = io::stdin();
+
+    if let Err(error) = Debugger::new(&read_program(path)).repl(stdin.lock(), io::stdout()) {
+        eprintln!("Debugger failed: {}", error);
+    }
+}
+
+/// Print the control-flow graph of the Intcode program at `path` as DOT.
+fn print_cfg(path: &str) {
+    print!("{}", Analysis::new(&read_program(path)).to_dot());
+}
+
+/// Print a listing of the Intcode program at `path`.
+fn print_disassembly(path: &str) {
+    for instruction in disassemble(&read_program(path)) {
+        println!("{}", instruction);
+    }
+}
+
+/// Run the Intcode program at `path` for at most `instructions` instructions,
+/// given `inputs` as comma-separated numbers, then print its memory in `range`
+/// (by default the program and the data around it) and the cells the run
+/// changed.
+fn dump(path: &str, instructions: usize, inputs: &str, range: Option<Range<usize>>) {
+    let mut intcomp = Intcomp::new(&read_program(path));
+    let before = intcomp.snapshot();
+
+    match parse_program(inputs) {
+        Ok(inputs) => inputs
+            .into_iter()
+            .for_each(|input| intcomp.send_input(input)),
+
+        Err(error) => {
+            eprintln!("Invalid inputs: {}", error);
+            return;
+        }
+    }
+
+    match intcomp.run_for(instructions) {
+        Ok(state) => println!(
+            "{:?} after {} instructions.",
+            state,
+            intcomp.instructions_executed()
+        ),
+
+        Err(error) => println!("Error: {}.", error),
+    }
+
+    let range = range.unwrap_or_else(|| intcomp.image_range());
+
+    println!("{}\n\nChanged:", intcomp.dump(range));
+
+    for change in before.diff(&intcomp.snapshot()) {
+        println!("{}", change);
+    }
+}
+
+/// Parse an address range written as `start..end`.
+fn parse_range(range: &str) -> Option<Range<usize>> {
+    let mut ends = range.splitn(2, "..");
+    let start = ends.next()?.parse().ok()?;
+    let end = ends.next()?.parse().ok()?;
+
+    if start <= end {
+        Some(start..end)
+    } else {
+        None
+    }
+}
+
+/// Save the Intcode program at `path` in binary form, for faster loading.
+fn pack(path: &str, output: &str) {
+    if let Err(error) = save_binary(&read_program(path), output) {
+        eprintln!("Could not save {}: {}", output, error);
+    }
+}
+
+/// Run the Intcode program at `path` with its I/O on stdin and stdout, then
+/// print a profile of the run to stderr as a table or as JSON.
+fn profile(path: &str, json: bool) {
+    let mut intcomp = Intcomp::new(&read_program(path));
+    let mut profiler = Profiler::new(&intcomp);
+
+    if let Err(error) = intcomp.execute_with_io(&mut profiler, &mut StdIo) {
+        eprintln!("Intcode program failed: {}", error);
+    }
+
+    if json {
+        eprintln!("{}", profiler.to_json());
+    } else {
+        eprint!("{}", profiler.table());
+    }
+}
+
+/// Print the error from a failed Intcode program without aborting the remaining days.
+fn report(result: Result<(), IntcompError>) {
+    if let Err(error) = result {
+        eprintln!("Intcode program failed: {}", error);
+    }
+}
 
 fn main() {
-    for arg in env::args().skip(1) {
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
         match arg.as_ref() {
             "1.1" => day01::part1(),
             "1.2" => day01::part2(),
-            "2.1" => day02::part1(),
-            "2.2" => day02::part2(),
+            "2.1" => report(day02::part1()),
+            "2.2" => report(day02::part2()),
             "3.1" => day03::part1(),
             "3.2" => day03::part2(),
             "4.1" => day04::part1(),
             "4.2" => day04::part2(),
-            "5.1" => day05::part1(),
-            "5.2" => day05::part2(),
+            "5.1" => report(day05::part1()),
+            "5.2" => report(day05::part2()),
             "6.1" => day06::part1(),
             "6.2" => day06::part2(),
-            "7.1" => day07::part1(),
-            "7.2" => day07::part2(),
+            "7.1" => report(day07::part1()),
+            "7.2" => report(day07::part2()),
             "8.1" => day08::part1(),
             "8.2" => day08::part2(),
-            "9.1" => day09::part1(),
-            "9.2" => day09::part2(),
+            "9.1" => report(day09::part1()),
+            "9.2" => report(day09::part2()),
             "10.1" => day10::part1(),
             "10.2" => day10::part2(),
-            "11.1" => day11::part1(),
-            "11.2" => day11::part2(),
+            "11.1" => report(day11::part1()),
+            "11.2" => report(day11::part2()),
             "12.1" => day12::part1(),
             "12.2" => day12::part2(),
-            "13.1" => day13::part1(),
-            "13.2" => day13::part2(),
+            "13.1" => report(day13::part1()),
+            "13.2" => report(day13::part2()),
             "14.1" => day14::part1(),
             "14.2" => day14::part2(),
-            "15.1" => day15::part1(),
-            "15.2" => day15::part2(),
+            "15.1" => report(day15::part1()),
+            "15.2" => report(day15::part2()),
             "16.1" => day16::part1(),
             "16.2" => day16::part2(),
-            "17.1" => day17::part1(),
-            "17.2" => day17::part2(),
+            "17.1" => report(day17::part1()),
+            "17.2" => report(day17::part2()),
+            "ascii" => match args.next() {
+                Some(path) => interact(&path),
+                None => eprintln!("Usage: ascii <program path>"),
+            },
+
+            "cfg" => match args.next() {
+                Some(path) => print_cfg(&path),
+                None => eprintln!("Usage: cfg <program path>"),
+            },
+
+            "compile" => match args.next() {
+                Some(output) => {
+                    if let Err(error) = compile(&output) {
+                        eprintln!("Could not compile to {}: {}", output, error);
+                    }
+                }
+
+                None => eprintln!("Usage: compile <output directory>"),
+            },
+
+            "debug" => match args.next() {
+                Some(path) => debug(&path),
+                None => eprintln!("Usage: debug <program path>"),
+            },
+
+            "disassemble" => match args.next() {
+                Some(path) => print_disassembly(&path),
+                None => eprintln!("Usage: disassemble <program path>"),
+            },
+
+            "dump" => match (
+                args.next(),
+                args.next().map(|count| count.parse()),
+                args.next().unwrap_or_default(),
+                args.next().map(|range| parse_range(&range)),
+            ) {
+                (Some(path), Some(Ok(instructions)), inputs, None) => {
+                    dump(&path, instructions, &inputs, None)
+                }
+
+                (Some(path), Some(Ok(instructions)), inputs, Some(Some(range))) => {
+                    dump(&path, instructions, &inputs, Some(range))
+                }
+
+                _ => eprintln!("Usage: dump <program path> <instructions> [inputs] [start..end]"),
+            },
+
+            "pack" => match (args.next(), args.next()) {
+                (Some(path), Some(output)) => pack(&path, &output),
+                _ => eprintln!("Usage: pack <program path> <output path>"),
+            },
+
+            "profile" => match args.next() {
+                Some(path) => profile(&path, false),
+                None => eprintln!("Usage: profile <program path>"),
+            },
+
+            "profile-json" => match args.next() {
+                Some(path) => profile(&path, true),
+                None => eprintln!("Usage: profile-json <program path>"),
+            },
+
             _ => eprintln!("Day {} hasn't been written yet!", arg),
         }
     }